@@ -0,0 +1,149 @@
+//! Partial presentation, through EGL_KHR_swap_buffers_with_damage or
+//! EGL_EXT_swap_buffers_with_damage.
+//!
+//! EGL_KHR_partial_update is not supported: it needs the damage before
+//! drawing starts (eglSetDamageRegionKHR), while damage is only known here
+//! when swapping.
+
+use euclid::Rect;
+use glutin::{self, GlContext};
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+#[cfg(target_os = "linux")]
+use std::os::raw::c_int;
+
+type EGLDisplay = *const c_void;
+type EGLSurface = *const c_void;
+type EGLint = i32;
+type EGLBoolean = u32;
+
+const EGL_EXTENSIONS: EGLint = 0x3055;
+const EGL_DRAW: EGLint = 0x3059;
+
+type QueryStringFn = unsafe extern "system" fn(EGLDisplay, EGLint) -> *const c_char;
+type GetCurrentDisplayFn = unsafe extern "system" fn() -> EGLDisplay;
+type GetCurrentSurfaceFn = unsafe extern "system" fn(EGLint) -> EGLSurface;
+type SwapBuffersWithDamageFn = unsafe extern "system" fn(EGLDisplay, EGLSurface, *const EGLint, EGLint)
+                                                         -> EGLBoolean;
+
+/// Core EGL functions.
+struct Egl {
+    query_string: QueryStringFn,
+    get_current_display: GetCurrentDisplayFn,
+    get_current_surface: GetCurrentSurfaceFn,
+}
+
+pub struct DamageSwapper {
+    get_current_display: GetCurrentDisplayFn,
+    get_current_surface: GetCurrentSurfaceFn,
+    swap_buffers_with_damage: SwapBuffersWithDamageFn,
+}
+
+impl DamageSwapper {
+    /// Returns None if the context is not an EGL one, or if none of the
+    /// damage extensions is available. The context must be current.
+    pub fn load(window: &glutin::GlWindow) -> Option<DamageSwapper> {
+        let egl = match Egl::load() {
+            Some(egl) => egl,
+            None => return None,
+        };
+        // Also null if the current context is not an EGL one.
+        let display = unsafe { (egl.get_current_display)() };
+        if display.is_null() {
+            return None;
+        }
+
+        let extensions = unsafe { (egl.query_string)(display, EGL_EXTENSIONS) };
+        if extensions.is_null() {
+            return None;
+        }
+        let extensions = unsafe { CStr::from_ptr(extensions) }.to_string_lossy().into_owned();
+        let name = ["KHR", "EXT"].iter().map(|vendor| format!("EGL_{}_swap_buffers_with_damage", vendor))
+            .find(|name| extensions.split(' ').any(|ext| ext == name));
+        let function = match name {
+            Some(ref name) if name.starts_with("EGL_KHR") => "eglSwapBuffersWithDamageKHR",
+            Some(_) => "eglSwapBuffersWithDamageEXT",
+            None => return None,
+        };
+        // Unlike core functions, extension functions can always be loaded
+        // through eglGetProcAddress.
+        let swap_buffers_with_damage = match window.get_proc_address(function) {
+            ptr if ptr.is_null() => return None,
+            ptr => unsafe { mem::transmute::<_, SwapBuffersWithDamageFn>(ptr) },
+        };
+        info!("Using {} for partial presentation", function);
+
+        Some(DamageSwapper {
+            get_current_display: egl.get_current_display,
+            get_current_surface: egl.get_current_surface,
+            swap_buffers_with_damage: swap_buffers_with_damage,
+        })
+    }
+
+    /// Rects are in window coordinates, with the origin at the top left.
+    /// The window's context must be current, so that the current surface is
+    /// the window's. Returns false if the swap failed.
+    pub fn swap(&self, damage: &[Rect<i32>], window_height: u32) -> bool {
+        let rects: Vec<EGLint> = damage.iter()
+            .flat_map(|rect| {
+                // EGL expects the origin at the bottom left.
+                let y = window_height as i32 - rect.origin.y - rect.size.height;
+                vec![rect.origin.x, y, rect.size.width, rect.size.height]
+            })
+            .collect();
+        unsafe {
+            let display = (self.get_current_display)();
+            let surface = (self.get_current_surface)(EGL_DRAW);
+            if display.is_null() || surface.is_null() {
+                return false;
+            }
+            (self.swap_buffers_with_damage)(display,
+                                            surface,
+                                            rects.as_ptr(),
+                                            damage.len() as EGLint) != 0
+        }
+    }
+}
+
+impl Egl {
+    /// eglGetProcAddress doesn't have to return core functions before EGL 1.5
+    /// (or without EGL_KHR_get_all_proc_addresses), so they are looked up in
+    /// libEGL, if glutin loaded it. The library is never closed.
+    #[cfg(target_os = "linux")]
+    fn load() -> Option<Egl> {
+        const RTLD_NOW: c_int = 2;
+        const RTLD_NOLOAD: c_int = 4;
+        extern "C" {
+            fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+            fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        }
+
+        let library = ["libEGL.so.1\0", "libEGL.so\0"]
+            .iter()
+            .map(|name| unsafe { dlopen(name.as_ptr() as *const c_char, RTLD_NOW | RTLD_NOLOAD) })
+            .find(|library| !library.is_null());
+        let library = match library {
+            Some(library) => library,
+            None => return None,
+        };
+        let load = |name: &str| unsafe { dlsym(library, name.as_ptr() as *const c_char) };
+        match (load("eglQueryString\0"), load("eglGetCurrentDisplay\0"), load("eglGetCurrentSurface\0")) {
+            (query_string, get_current_display, get_current_surface)
+                if !query_string.is_null() && !get_current_display.is_null() &&
+                   !get_current_surface.is_null() => unsafe {
+                Some(Egl {
+                    query_string: mem::transmute::<_, QueryStringFn>(query_string),
+                    get_current_display: mem::transmute::<_, GetCurrentDisplayFn>(get_current_display),
+                    get_current_surface: mem::transmute::<_, GetCurrentSurfaceFn>(get_current_surface),
+                })
+            },
+            _ => None,
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn load() -> Option<Egl> {
+        None
+    }
+}
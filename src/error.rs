@@ -0,0 +1,67 @@
+use glutin;
+use std::error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The window or its context couldn't be created.
+    Creation(glutin::CreationError),
+    /// A context operation failed. `ContextError::ContextLost` means the
+    /// context has to be rebuilt, see `GLWindow::recreate_context`.
+    Context(glutin::ContextError),
+    /// The windowing backend can't do this, or not in this configuration.
+    Unsupported(&'static str),
+    /// The windowing backend reported an error.
+    Platform(String),
+}
+
+impl Error {
+    pub fn is_context_lost(&self) -> bool {
+        match *self {
+            Error::Context(glutin::ContextError::ContextLost) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Creation(ref err) => write!(f, "Creation error: {}", err),
+            Error::Context(ref err) => write!(f, "Context error: {}", err),
+            Error::Unsupported(what) => write!(f, "Unsupported: {}", what),
+            Error::Platform(ref err) => write!(f, "Platform error: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Creation(ref err) => err.description(),
+            Error::Context(ref err) => err.description(),
+            Error::Unsupported(_) => "Unsupported by the windowing backend",
+            Error::Platform(ref err) => err,
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::Creation(ref err) => Some(err),
+            Error::Context(ref err) => Some(err),
+            Error::Unsupported(_) | Error::Platform(_) => None,
+        }
+    }
+}
+
+impl From<glutin::CreationError> for Error {
+    fn from(err: glutin::CreationError) -> Error {
+        Error::Creation(err)
+    }
+}
+
+impl From<glutin::ContextError> for Error {
+    fn from(err: glutin::ContextError) -> Error {
+        Error::Context(err)
+    }
+}
@@ -0,0 +1,478 @@
+//! A `Gl` wrapper checking glGetError after every call, to find out which
+//! call caused an error.
+
+use gleam::gl::{self, DebugMessage, GlType, GLbitfield, GLboolean, GLclampf, GLeglImageOES, GLenum,
+                GLfloat, GLint, GLint64, GLintptr, GLsizei, GLsizeiptr, GLsync, GLuint, GLuint64,
+                GLvoid};
+use gl_debug;
+use std::os::raw::c_int;
+use std::rc::Rc;
+
+pub struct ErrorCheckingGl {
+    gl: Rc<gl::Gl>,
+}
+
+impl ErrorCheckingGl {
+    pub fn new(gl: Rc<gl::Gl>) -> ErrorCheckingGl {
+        ErrorCheckingGl {
+            gl: gl,
+        }
+    }
+
+    fn check(&self, function: &str) {
+        for error in gl_debug::drain_errors(&*self.gl) {
+            error!("GL error 0x{:x} in {}", error, function);
+        }
+    }
+}
+
+/// Delegate a method to the wrapped `Gl`, then check for errors.
+macro_rules! checked {
+    (fn $name:ident(&self $(, $arg:ident: $ty:ty)*)) => {
+        fn $name(&self $(, $arg: $ty)*) {
+            self.gl.$name($($arg),*);
+            self.check(stringify!($name));
+        }
+    };
+    (fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty) => {
+        fn $name(&self $(, $arg: $ty)*) -> $ret {
+            let result = self.gl.$name($($arg),*);
+            self.check(stringify!($name));
+            result
+        }
+    };
+}
+
+impl gl::Gl for ErrorCheckingGl {
+    fn get_type(&self) -> GlType {
+        self.gl.get_type()
+    }
+
+    // Not checked, as checking would swallow the error.
+    fn get_error(&self) -> GLenum {
+        self.gl.get_error()
+    }
+
+    checked!(fn buffer_data_untyped(&self,
+                                    target: GLenum,
+                                    size: GLsizeiptr,
+                                    data: *const GLvoid,
+                                    usage: GLenum));
+    checked!(fn buffer_sub_data_untyped(&self,
+                                        target: GLenum,
+                                        offset: isize,
+                                        size: GLsizeiptr,
+                                        data: *const GLvoid));
+    checked!(fn tex_buffer(&self, target: GLenum, internal_format: GLenum, buffer: GLuint));
+    checked!(fn shader_source(&self, shader: GLuint, strings: &[&[u8]]));
+    checked!(fn read_buffer(&self, mode: GLenum));
+    checked!(fn read_pixels_into_buffer(&self,
+                                        x: GLint,
+                                        y: GLint,
+                                        width: GLsizei,
+                                        height: GLsizei,
+                                        format: GLenum,
+                                        pixel_type: GLenum,
+                                        dst_buffer: &mut [u8]));
+    checked!(fn read_pixels(&self,
+                            x: GLint,
+                            y: GLint,
+                            width: GLsizei,
+                            height: GLsizei,
+                            format: GLenum,
+                            pixel_type: GLenum) -> Vec<u8>);
+    checked!(fn sample_coverage(&self, value: GLclampf, invert: bool));
+    checked!(fn polygon_offset(&self, factor: GLfloat, units: GLfloat));
+    checked!(fn pixel_store_i(&self, name: GLenum, param: GLint));
+    checked!(fn gen_buffers(&self, n: GLsizei) -> Vec<GLuint>);
+    checked!(fn gen_renderbuffers(&self, n: GLsizei) -> Vec<GLuint>);
+    checked!(fn gen_framebuffers(&self, n: GLsizei) -> Vec<GLuint>);
+    checked!(fn gen_textures(&self, n: GLsizei) -> Vec<GLuint>);
+    checked!(fn gen_vertex_arrays(&self, n: GLsizei) -> Vec<GLuint>);
+    checked!(fn gen_queries(&self, n: GLsizei) -> Vec<GLuint>);
+    checked!(fn begin_query(&self, target: GLenum, id: GLuint));
+    checked!(fn end_query(&self, target: GLenum));
+    checked!(fn query_counter(&self, id: GLuint, target: GLenum));
+    checked!(fn get_query_object_iv(&self, id: GLuint, pname: GLenum) -> i32);
+    checked!(fn get_query_object_uiv(&self, id: GLuint, pname: GLenum) -> u32);
+    checked!(fn get_query_object_i64v(&self, id: GLuint, pname: GLenum) -> i64);
+    checked!(fn get_query_object_ui64v(&self, id: GLuint, pname: GLenum) -> u64);
+    checked!(fn delete_queries(&self, queries: &[GLuint]));
+    checked!(fn delete_vertex_arrays(&self, vertex_arrays: &[GLuint]));
+    checked!(fn delete_buffers(&self, buffers: &[GLuint]));
+    checked!(fn delete_renderbuffers(&self, renderbuffers: &[GLuint]));
+    checked!(fn delete_framebuffers(&self, framebuffers: &[GLuint]));
+    checked!(fn delete_textures(&self, textures: &[GLuint]));
+    checked!(fn framebuffer_renderbuffer(&self,
+                                         target: GLenum,
+                                         attachment: GLenum,
+                                         renderbuffertarget: GLenum,
+                                         renderbuffer: GLuint));
+    checked!(fn renderbuffer_storage(&self,
+                                     target: GLenum,
+                                     internalformat: GLenum,
+                                     width: GLsizei,
+                                     height: GLsizei));
+    checked!(fn depth_func(&self, func: GLenum));
+    checked!(fn active_texture(&self, texture: GLenum));
+    checked!(fn attach_shader(&self, program: GLuint, shader: GLuint));
+    checked!(fn bind_attrib_location(&self, program: GLuint, index: GLuint, name: &str));
+    checked!(fn get_uniform_iv(&self, program: GLuint, location: GLint) -> Vec<GLint>);
+    checked!(fn get_uniform_fv(&self, program: GLuint, location: GLint) -> Vec<GLfloat>);
+    checked!(fn get_uniform_block_index(&self, program: GLuint, name: &str) -> GLuint);
+    checked!(fn get_uniform_indices(&self, program: GLuint, names: &[&str]) -> Vec<GLuint>);
+    checked!(fn bind_buffer_base(&self, target: GLenum, index: GLuint, buffer: GLuint));
+    checked!(fn bind_buffer_range(&self,
+                                  target: GLenum,
+                                  index: GLuint,
+                                  buffer: GLuint,
+                                  offset: GLintptr,
+                                  size: GLsizeiptr));
+    checked!(fn uniform_block_binding(&self,
+                                      program: GLuint,
+                                      uniform_block_index: GLuint,
+                                      uniform_block_binding: GLuint));
+    checked!(fn bind_buffer(&self, target: GLenum, buffer: GLuint));
+    checked!(fn bind_vertex_array(&self, vao: GLuint));
+    checked!(fn bind_renderbuffer(&self, target: GLenum, renderbuffer: GLuint));
+    checked!(fn bind_framebuffer(&self, target: GLenum, framebuffer: GLuint));
+    checked!(fn bind_texture(&self, target: GLenum, texture: GLuint));
+    checked!(fn draw_buffers(&self, bufs: &[GLenum]));
+    checked!(fn tex_image_2d(&self,
+                             target: GLenum,
+                             level: GLint,
+                             internal_format: GLint,
+                             width: GLsizei,
+                             height: GLsizei,
+                             border: GLint,
+                             format: GLenum,
+                             ty: GLenum,
+                             opt_data: Option<&[u8]>));
+    checked!(fn compressed_tex_image_2d(&self,
+                                        target: GLenum,
+                                        level: GLint,
+                                        internal_format: GLenum,
+                                        width: GLsizei,
+                                        height: GLsizei,
+                                        border: GLint,
+                                        data: &[u8]));
+    checked!(fn compressed_tex_sub_image_2d(&self,
+                                            target: GLenum,
+                                            level: GLint,
+                                            xoffset: GLint,
+                                            yoffset: GLint,
+                                            width: GLsizei,
+                                            height: GLsizei,
+                                            format: GLenum,
+                                            data: &[u8]));
+    checked!(fn tex_image_3d(&self,
+                             target: GLenum,
+                             level: GLint,
+                             internal_format: GLint,
+                             width: GLsizei,
+                             height: GLsizei,
+                             depth: GLsizei,
+                             border: GLint,
+                             format: GLenum,
+                             ty: GLenum,
+                             opt_data: Option<&[u8]>));
+    checked!(fn copy_tex_image_2d(&self,
+                                  target: GLenum,
+                                  level: GLint,
+                                  internal_format: GLenum,
+                                  x: GLint,
+                                  y: GLint,
+                                  width: GLsizei,
+                                  height: GLsizei,
+                                  border: GLint));
+    checked!(fn copy_tex_sub_image_2d(&self,
+                                      target: GLenum,
+                                      level: GLint,
+                                      xoffset: GLint,
+                                      yoffset: GLint,
+                                      x: GLint,
+                                      y: GLint,
+                                      width: GLsizei,
+                                      height: GLsizei));
+    checked!(fn copy_tex_sub_image_3d(&self,
+                                      target: GLenum,
+                                      level: GLint,
+                                      xoffset: GLint,
+                                      yoffset: GLint,
+                                      zoffset: GLint,
+                                      x: GLint,
+                                      y: GLint,
+                                      width: GLsizei,
+                                      height: GLsizei));
+    checked!(fn tex_sub_image_2d(&self,
+                                 target: GLenum,
+                                 level: GLint,
+                                 xoffset: GLint,
+                                 yoffset: GLint,
+                                 width: GLsizei,
+                                 height: GLsizei,
+                                 format: GLenum,
+                                 ty: GLenum,
+                                 data: &[u8]));
+    checked!(fn tex_sub_image_2d_pbo(&self,
+                                     target: GLenum,
+                                     level: GLint,
+                                     xoffset: GLint,
+                                     yoffset: GLint,
+                                     width: GLsizei,
+                                     height: GLsizei,
+                                     format: GLenum,
+                                     ty: GLenum,
+                                     offset: usize));
+    checked!(fn tex_sub_image_3d(&self,
+                                 target: GLenum,
+                                 level: GLint,
+                                 xoffset: GLint,
+                                 yoffset: GLint,
+                                 zoffset: GLint,
+                                 width: GLsizei,
+                                 height: GLsizei,
+                                 depth: GLsizei,
+                                 format: GLenum,
+                                 ty: GLenum,
+                                 data: &[u8]));
+    checked!(fn tex_sub_image_3d_pbo(&self,
+                                     target: GLenum,
+                                     level: GLint,
+                                     xoffset: GLint,
+                                     yoffset: GLint,
+                                     zoffset: GLint,
+                                     width: GLsizei,
+                                     height: GLsizei,
+                                     depth: GLsizei,
+                                     format: GLenum,
+                                     ty: GLenum,
+                                     offset: usize));
+    checked!(fn get_tex_image_into_buffer(&self,
+                                          target: GLenum,
+                                          level: GLint,
+                                          format: GLenum,
+                                          ty: GLenum,
+                                          output: &mut [u8]));
+    checked!(fn get_integer_v(&self, name: GLenum) -> GLint);
+    checked!(fn get_integer_64v(&self, name: GLenum) -> GLint64);
+    checked!(fn get_integer_iv(&self, name: GLenum, index: GLuint) -> GLint);
+    checked!(fn get_integer_64iv(&self, name: GLenum, index: GLuint) -> GLint64);
+    checked!(fn get_boolean_v(&self, name: GLenum) -> GLboolean);
+    checked!(fn get_float_v(&self, name: GLenum) -> GLfloat);
+    checked!(fn get_framebuffer_attachment_parameter_iv(&self,
+                                                        target: GLenum,
+                                                        attachment: GLenum,
+                                                        pname: GLenum) -> GLint);
+    checked!(fn get_renderbuffer_parameter_iv(&self, target: GLenum, pname: GLenum) -> GLint);
+    checked!(fn get_tex_parameter_iv(&self, target: GLenum, name: GLenum) -> GLint);
+    checked!(fn get_tex_parameter_fv(&self, target: GLenum, name: GLenum) -> GLfloat);
+    checked!(fn tex_parameter_i(&self, target: GLenum, pname: GLenum, param: GLint));
+    checked!(fn tex_parameter_f(&self, target: GLenum, pname: GLenum, param: GLfloat));
+    checked!(fn framebuffer_texture_2d(&self,
+                                       target: GLenum,
+                                       attachment: GLenum,
+                                       textarget: GLenum,
+                                       texture: GLuint,
+                                       level: GLint));
+    checked!(fn framebuffer_texture_layer(&self,
+                                          target: GLenum,
+                                          attachment: GLenum,
+                                          texture: GLuint,
+                                          level: GLint,
+                                          layer: GLint));
+    checked!(fn blit_framebuffer(&self,
+                                 src_x0: GLint,
+                                 src_y0: GLint,
+                                 src_x1: GLint,
+                                 src_y1: GLint,
+                                 dst_x0: GLint,
+                                 dst_y0: GLint,
+                                 dst_x1: GLint,
+                                 dst_y1: GLint,
+                                 mask: GLbitfield,
+                                 filter: GLenum));
+    checked!(fn vertex_attrib_4f(&self,
+                                 index: GLuint,
+                                 x: GLfloat,
+                                 y: GLfloat,
+                                 z: GLfloat,
+                                 w: GLfloat));
+    checked!(fn vertex_attrib_pointer_f32(&self,
+                                          index: GLuint,
+                                          size: GLint,
+                                          normalized: bool,
+                                          stride: GLsizei,
+                                          offset: GLuint));
+    checked!(fn vertex_attrib_pointer(&self,
+                                      index: GLuint,
+                                      size: GLint,
+                                      type_: GLenum,
+                                      normalized: bool,
+                                      stride: GLsizei,
+                                      offset: GLuint));
+    checked!(fn vertex_attrib_i_pointer(&self,
+                                        index: GLuint,
+                                        size: GLint,
+                                        type_: GLenum,
+                                        stride: GLsizei,
+                                        offset: GLuint));
+    checked!(fn vertex_attrib_divisor(&self, index: GLuint, divisor: GLuint));
+    checked!(fn viewport(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei));
+    checked!(fn get_viewport(&self) -> (GLint, GLint, GLsizei, GLsizei));
+    checked!(fn scissor(&self, x: GLint, y: GLint, width: GLsizei, height: GLsizei));
+    checked!(fn line_width(&self, width: GLfloat));
+    checked!(fn use_program(&self, program: GLuint));
+    checked!(fn validate_program(&self, program: GLuint));
+    checked!(fn draw_arrays(&self, mode: GLenum, first: GLint, count: GLsizei));
+    checked!(fn draw_arrays_instanced(&self,
+                                      mode: GLenum,
+                                      first: GLint,
+                                      count: GLsizei,
+                                      primcount: GLsizei));
+    checked!(fn draw_elements(&self,
+                              mode: GLenum,
+                              count: GLsizei,
+                              element_type: GLenum,
+                              indices_offset: GLuint));
+    checked!(fn draw_elements_instanced(&self,
+                                        mode: GLenum,
+                                        count: GLsizei,
+                                        element_type: GLenum,
+                                        indices_offset: GLuint,
+                                        primcount: GLsizei));
+    checked!(fn blend_color(&self, r: f32, g: f32, b: f32, a: f32));
+    checked!(fn blend_func(&self, sfactor: GLenum, dfactor: GLenum));
+    checked!(fn blend_func_separate(&self,
+                                    src_rgb: GLenum,
+                                    dest_rgb: GLenum,
+                                    src_alpha: GLenum,
+                                    dest_alpha: GLenum));
+    checked!(fn blend_equation(&self, mode: GLenum));
+    checked!(fn blend_equation_separate(&self, mode_rgb: GLenum, mode_alpha: GLenum));
+    checked!(fn color_mask(&self, r: bool, g: bool, b: bool, a: bool));
+    checked!(fn cull_face(&self, mode: GLenum));
+    checked!(fn front_face(&self, mode: GLenum));
+    checked!(fn enable(&self, cap: GLenum));
+    checked!(fn disable(&self, cap: GLenum));
+    checked!(fn hint(&self, param_name: GLenum, param_val: GLenum));
+    checked!(fn is_enabled(&self, cap: GLenum) -> GLboolean);
+    checked!(fn is_shader(&self, shader: GLuint) -> GLboolean);
+    checked!(fn is_texture(&self, texture: GLenum) -> GLboolean);
+    checked!(fn is_framebuffer(&self, framebuffer: GLenum) -> GLboolean);
+    checked!(fn is_renderbuffer(&self, renderbuffer: GLenum) -> GLboolean);
+    checked!(fn check_frame_buffer_status(&self, target: GLenum) -> GLenum);
+    checked!(fn enable_vertex_attrib_array(&self, index: GLuint));
+    checked!(fn disable_vertex_attrib_array(&self, index: GLuint));
+    checked!(fn uniform_1f(&self, location: GLint, v0: GLfloat));
+    checked!(fn uniform_1fv(&self, location: GLint, values: &[f32]));
+    checked!(fn uniform_1i(&self, location: GLint, v0: GLint));
+    checked!(fn uniform_1iv(&self, location: GLint, values: &[i32]));
+    checked!(fn uniform_1ui(&self, location: GLint, v0: GLuint));
+    checked!(fn uniform_2f(&self, location: GLint, v0: GLfloat, v1: GLfloat));
+    checked!(fn uniform_2fv(&self, location: GLint, values: &[f32]));
+    checked!(fn uniform_2i(&self, location: GLint, v0: GLint, v1: GLint));
+    checked!(fn uniform_2iv(&self, location: GLint, values: &[i32]));
+    checked!(fn uniform_2ui(&self, location: GLint, v0: GLuint, v1: GLuint));
+    checked!(fn uniform_3f(&self, location: GLint, v0: GLfloat, v1: GLfloat, v2: GLfloat));
+    checked!(fn uniform_3fv(&self, location: GLint, values: &[f32]));
+    checked!(fn uniform_3i(&self, location: GLint, v0: GLint, v1: GLint, v2: GLint));
+    checked!(fn uniform_3iv(&self, location: GLint, values: &[i32]));
+    checked!(fn uniform_3ui(&self, location: GLint, v0: GLuint, v1: GLuint, v2: GLuint));
+    checked!(fn uniform_4f(&self, location: GLint, x: GLfloat, y: GLfloat, z: GLfloat, w: GLfloat));
+    checked!(fn uniform_4i(&self, location: GLint, x: GLint, y: GLint, z: GLint, w: GLint));
+    checked!(fn uniform_4iv(&self, location: GLint, values: &[i32]));
+    checked!(fn uniform_4ui(&self, location: GLint, x: GLuint, y: GLuint, z: GLuint, w: GLuint));
+    checked!(fn uniform_4fv(&self, location: GLint, values: &[f32]));
+    checked!(fn uniform_matrix_2fv(&self, location: GLint, transpose: bool, value: &[f32]));
+    checked!(fn uniform_matrix_3fv(&self, location: GLint, transpose: bool, value: &[f32]));
+    checked!(fn uniform_matrix_4fv(&self, location: GLint, transpose: bool, value: &[f32]));
+    checked!(fn depth_mask(&self, flag: bool));
+    checked!(fn depth_range(&self, near: f64, far: f64));
+    checked!(fn get_active_attrib(&self, program: GLuint, index: GLuint) -> (i32, u32, String));
+    checked!(fn get_active_uniform(&self, program: GLuint, index: GLuint) -> (i32, u32, String));
+    checked!(fn get_active_uniforms_iv(&self,
+                                       program: GLuint,
+                                       indices: Vec<GLuint>,
+                                       pname: GLenum) -> Vec<GLint>);
+    checked!(fn get_active_uniform_block_i(&self,
+                                           program: GLuint,
+                                           index: GLuint,
+                                           pname: GLenum) -> GLint);
+    checked!(fn get_active_uniform_block_iv(&self,
+                                            program: GLuint,
+                                            index: GLuint,
+                                            pname: GLenum) -> Vec<GLint>);
+    checked!(fn get_active_uniform_block_name(&self, program: GLuint, index: GLuint) -> String);
+    checked!(fn get_attrib_location(&self, program: GLuint, name: &str) -> c_int);
+    checked!(fn get_frag_data_location(&self, program: GLuint, name: &str) -> c_int);
+    checked!(fn get_uniform_location(&self, program: GLuint, name: &str) -> c_int);
+    checked!(fn get_program_info_log(&self, program: GLuint) -> String);
+    checked!(fn get_program_iv(&self, program: GLuint, pname: GLenum) -> GLint);
+    checked!(fn get_program_binary(&self, program: GLuint) -> (Vec<u8>, GLenum));
+    checked!(fn program_binary(&self, program: GLuint, format: GLenum, binary: &[u8]));
+    checked!(fn program_parameter_i(&self, program: GLuint, pname: GLenum, value: GLint));
+    checked!(fn get_vertex_attrib_iv(&self, index: GLuint, pname: GLenum) -> GLint);
+    checked!(fn get_vertex_attrib_fv(&self, index: GLuint, pname: GLenum) -> Vec<GLfloat>);
+    checked!(fn get_vertex_attrib_pointer_v(&self, index: GLuint, pname: GLenum) -> GLsizeiptr);
+    checked!(fn get_buffer_parameter_iv(&self, target: GLuint, pname: GLenum) -> GLint);
+    checked!(fn get_shader_info_log(&self, shader: GLuint) -> String);
+    checked!(fn get_string(&self, which: GLenum) -> String);
+    checked!(fn get_string_i(&self, which: GLenum, index: GLuint) -> String);
+    checked!(fn get_shader_iv(&self, shader: GLuint, pname: GLenum) -> GLint);
+    checked!(fn get_shader_precision_format(&self,
+                                            shader_type: GLuint,
+                                            precision_type: GLuint) -> (GLint, GLint, GLint));
+    checked!(fn compile_shader(&self, shader: GLuint));
+    checked!(fn create_program(&self) -> GLuint);
+    checked!(fn delete_program(&self, program: GLuint));
+    checked!(fn create_shader(&self, shader_type: GLenum) -> GLuint);
+    checked!(fn delete_shader(&self, shader: GLuint));
+    checked!(fn detach_shader(&self, program: GLuint, shader: GLuint));
+    checked!(fn link_program(&self, program: GLuint));
+    checked!(fn clear_color(&self, r: f32, g: f32, b: f32, a: f32));
+    checked!(fn clear(&self, buffer_mask: GLbitfield));
+    checked!(fn clear_depth(&self, depth: f64));
+    checked!(fn clear_stencil(&self, s: GLint));
+    checked!(fn flush(&self));
+    checked!(fn finish(&self));
+    checked!(fn stencil_mask(&self, mask: GLuint));
+    checked!(fn stencil_mask_separate(&self, face: GLenum, mask: GLuint));
+    checked!(fn stencil_func(&self, func: GLenum, ref_: GLint, mask: GLuint));
+    checked!(fn stencil_func_separate(&self,
+                                      face: GLenum,
+                                      func: GLenum,
+                                      ref_: GLint,
+                                      mask: GLuint));
+    checked!(fn stencil_op(&self, sfail: GLenum, dpfail: GLenum, dppass: GLenum));
+    checked!(fn stencil_op_separate(&self,
+                                    face: GLenum,
+                                    sfail: GLenum,
+                                    dpfail: GLenum,
+                                    dppass: GLenum));
+    checked!(fn egl_image_target_texture2d_oes(&self, target: GLenum, image: GLeglImageOES));
+    checked!(fn generate_mipmap(&self, target: GLenum));
+    checked!(fn insert_event_marker_ext(&self, message: &str));
+    checked!(fn push_group_marker_ext(&self, message: &str));
+    checked!(fn pop_group_marker_ext(&self));
+    checked!(fn fence_sync(&self, condition: GLenum, flags: GLbitfield) -> GLsync);
+    checked!(fn client_wait_sync(&self, sync: GLsync, flags: GLbitfield, timeout: GLuint64));
+    checked!(fn wait_sync(&self, sync: GLsync, flags: GLbitfield, timeout: GLuint64));
+    checked!(fn delete_sync(&self, sync: GLsync));
+    checked!(fn texture_range_apple(&self, target: GLenum, data: &[u8]));
+    checked!(fn gen_fences_apple(&self, n: GLsizei) -> Vec<GLuint>);
+    checked!(fn delete_fences_apple(&self, fences: &[GLuint]));
+    checked!(fn set_fence_apple(&self, fence: GLuint));
+    checked!(fn finish_fence_apple(&self, fence: GLuint));
+    checked!(fn test_fence_apple(&self, fence: GLuint));
+    checked!(fn bind_frag_data_location_indexed(&self,
+                                                program: GLuint,
+                                                color_number: GLuint,
+                                                index: GLuint,
+                                                name: &str));
+    checked!(fn get_frag_data_index(&self, program: GLuint, name: &str) -> GLint);
+    checked!(fn alias_point_size_range(&self) -> (GLfloat, GLfloat));
+    checked!(fn alias_line_width_range(&self) -> (GLfloat, GLfloat));
+    checked!(fn max_viewport_dims(&self) -> (GLint, GLint));
+    checked!(fn get_debug_messages(&self) -> Vec<DebugMessage>);
+}
@@ -0,0 +1,176 @@
+//! Pacing of the `GLEvent::Frame` events.
+
+use std::time::{Duration, Instant};
+
+/// Assume a 60Hz display, as glutin doesn't tell us the refresh rate.
+pub fn frame_interval() -> Duration {
+    Duration::new(0, 16_666_667)
+}
+
+/// Decides when to send the next `GLEvent::Frame` of a window.
+///
+/// With vsync, swap_buffers blocks until the next refresh, so a frame is
+/// sent as soon as the previous one has been presented. If swaps happen
+/// faster than the display refresh rate, vsync is not effective and frames
+/// are paced with a timer instead. As a timer hides whether vsync works
+/// again (e.g. once the window is back on screen), vsync is retried now and
+/// then.
+#[derive(Debug)]
+pub struct FramePacer {
+    pub redraw_requested: bool,
+    last_frame: Instant,
+    last_swap: Option<Instant>,
+    swapped_since_frame: bool,
+    vsync: bool,
+    /// Consecutive swaps that didn't block.
+    fast_swaps: u32,
+    /// When vsync got disabled.
+    vsync_disabled_at: Option<Instant>,
+}
+
+/// Consecutive fast swaps needed before falling back to a timer, so that a
+/// single late frame doesn't disable vsync.
+const FAST_SWAPS_THRESHOLD: u32 = 5;
+
+/// How long to pace frames with a timer before trying vsync again.
+fn vsync_retry_delay() -> Duration {
+    Duration::from_secs(10)
+}
+
+impl FramePacer {
+    pub fn new(vsync: bool) -> FramePacer {
+        FramePacer {
+            redraw_requested: false,
+            last_frame: Instant::now(),
+            last_swap: None,
+            swapped_since_frame: true,
+            vsync: vsync,
+            fast_swaps: 0,
+            vsync_disabled_at: None,
+        }
+    }
+
+    pub fn next_frame(&self) -> Option<Instant> {
+        if !self.redraw_requested {
+            None
+        } else if self.vsync && self.swapped_since_frame {
+            Some(self.last_frame)
+        } else {
+            Some(self.last_frame + frame_interval())
+        }
+    }
+
+    pub fn on_frame(&mut self, now: Instant) {
+        self.redraw_requested = false;
+        self.swapped_since_frame = false;
+        self.last_frame = now;
+    }
+
+    pub fn on_swap(&mut self, now: Instant) {
+        match (self.vsync, self.last_swap, self.vsync_disabled_at) {
+            (true, Some(last_swap), _) => {
+                if now - last_swap < frame_interval() * 3 / 4 {
+                    self.fast_swaps += 1;
+                } else {
+                    self.fast_swaps = 0;
+                }
+                if self.fast_swaps >= FAST_SWAPS_THRESHOLD {
+                    info!("Vsync doesn't seem to be effective. Falling back to a timer.");
+                    self.vsync = false;
+                    self.vsync_disabled_at = Some(now);
+                }
+            }
+            (false, _, Some(disabled_at)) if now - disabled_at >= vsync_retry_delay() => {
+                debug!("Trying vsync again.");
+                self.vsync = true;
+                self.fast_swaps = 0;
+                self.vsync_disabled_at = None;
+            }
+            _ => {}
+        }
+        self.last_swap = Some(now);
+        self.swapped_since_frame = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// Request a redraw and swap right away, `times` times, `interval`
+    /// apart. Returns when the last frame was sent.
+    fn draw(pacer: &mut FramePacer, start: Instant, interval: Duration, times: u32) -> Instant {
+        let mut now = start;
+        for i in 0..times {
+            now = start + interval * i;
+            pacer.redraw_requested = true;
+            pacer.on_frame(now);
+            pacer.on_swap(now);
+        }
+        now
+    }
+
+    #[test]
+    fn falls_back_to_a_timer_after_fast_swaps() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(true);
+        // The first swap has nothing to compare to.
+        let now = draw(&mut pacer, start, ms(1), FAST_SWAPS_THRESHOLD);
+        assert!(pacer.vsync);
+        let now = draw(&mut pacer, now + ms(1), ms(1), 1);
+        assert!(!pacer.vsync);
+        pacer.redraw_requested = true;
+        assert_eq!(pacer.next_frame(), Some(now + frame_interval()));
+    }
+
+    #[test]
+    fn a_slow_swap_resets_the_fast_swaps() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(true);
+        let now = draw(&mut pacer, start, ms(1), FAST_SWAPS_THRESHOLD);
+        let now = draw(&mut pacer, now + frame_interval(), ms(1), FAST_SWAPS_THRESHOLD);
+        assert!(pacer.vsync);
+        draw(&mut pacer, now + ms(1), ms(1), 1);
+        assert!(!pacer.vsync);
+    }
+
+    #[test]
+    fn retries_vsync_after_a_while() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(true);
+        let disabled_at = draw(&mut pacer, start, ms(1), FAST_SWAPS_THRESHOLD + 1);
+        assert!(!pacer.vsync);
+        draw(&mut pacer, disabled_at + vsync_retry_delay() - ms(1), ms(1), 1);
+        assert!(!pacer.vsync);
+        let now = draw(&mut pacer, disabled_at + vsync_retry_delay(), ms(1), 1);
+        assert!(pacer.vsync);
+        pacer.redraw_requested = true;
+        assert_eq!(pacer.next_frame(), Some(now));
+    }
+
+    #[test]
+    fn at_most_one_frame_per_refresh() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(true);
+        assert_eq!(pacer.next_frame(), None);
+        pacer.redraw_requested = true;
+        pacer.on_frame(start);
+        // Requested again before the previous frame got swapped.
+        pacer.redraw_requested = true;
+        assert_eq!(pacer.next_frame(), Some(start + frame_interval()));
+        // With vsync, the swap waited for the refresh already.
+        pacer.on_swap(start + frame_interval());
+        assert_eq!(pacer.next_frame(), Some(start));
+
+        let mut pacer = FramePacer::new(false);
+        pacer.redraw_requested = true;
+        pacer.on_frame(start);
+        pacer.on_swap(start);
+        pacer.redraw_requested = true;
+        assert_eq!(pacer.next_frame(), Some(start + frame_interval()));
+    }
+}
@@ -0,0 +1,248 @@
+//! Frame timing statistics, recorded every time a window swaps its buffers.
+
+use gleam::gl::{self, GLenum, GLfloat, GLint};
+use glutin::{self, GlContext};
+use std::cmp;
+use std::collections::VecDeque;
+use std::mem;
+use std::time::{Duration, Instant};
+
+/// How many frames are kept around.
+const HISTORY_LEN: usize = 120;
+
+/// Width and height of the overlay, in device pixels.
+pub const OVERLAY_SIZE: (i32, i32) = (HISTORY_LEN as i32 * BAR_WIDTH, MAX_HEIGHT);
+const BAR_WIDTH: i32 = 2;
+const MAX_HEIGHT: i32 = 100;
+
+/// Gaps longer than this are considered idle time, not slow frames.
+const IDLE_THRESHOLD_MS: f64 = 250.;
+
+#[derive(Clone, Debug)]
+pub struct FrameStats {
+    /// Time between consecutive swaps, oldest first.
+    pub frame_times: Vec<Duration>,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    /// Number of display refreshes missed since the window got created.
+    pub dropped_frames: u64,
+    /// Measured over the recent frames.
+    pub fps: f32,
+}
+
+pub struct FrameTimings {
+    last_swap: Option<Instant>,
+    frame_times: VecDeque<Duration>,
+    dropped_frames: u64,
+}
+
+impl FrameTimings {
+    pub fn new() -> FrameTimings {
+        FrameTimings {
+            last_swap: None,
+            frame_times: VecDeque::with_capacity(HISTORY_LEN),
+            dropped_frames: 0,
+        }
+    }
+
+    pub fn record_swap(&mut self, now: Instant, refresh_interval: Duration) {
+        if let Some(last_swap) = self.last_swap {
+            let frame_time = now - last_swap;
+            let frame_ms = to_ms(frame_time);
+            if frame_ms < IDLE_THRESHOLD_MS {
+                let missed = (frame_ms / to_ms(refresh_interval)).round() as u64;
+                self.dropped_frames += missed.saturating_sub(1);
+                if self.frame_times.len() == HISTORY_LEN {
+                    self.frame_times.pop_front();
+                }
+                self.frame_times.push_back(frame_time);
+            }
+        }
+        self.last_swap = Some(now);
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        let frame_times: Vec<Duration> = self.frame_times.iter().cloned().collect();
+        let mut sorted = frame_times.clone();
+        sorted.sort();
+        let percentile = |p: f64| -> Duration {
+            if sorted.is_empty() {
+                return Duration::new(0, 0);
+            }
+            let idx = ((sorted.len() - 1) as f64 * p).round() as usize;
+            sorted[idx]
+        };
+        let total_ms: f64 = frame_times.iter().map(|&t| to_ms(t)).sum();
+        let fps = if total_ms > 0. {
+            (frame_times.len() as f64 * 1000. / total_ms) as f32
+        } else {
+            0.
+        };
+        FrameStats {
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            dropped_frames: self.dropped_frames,
+            fps: fps,
+            frame_times: frame_times,
+        }
+    }
+}
+
+fn to_ms(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000. + duration.subsec_nanos() as f64 / 1_000_000.
+}
+
+type GetIntegervFn = unsafe extern "system" fn(GLenum, *mut GLint);
+type GetFloatvFn = unsafe extern "system" fn(GLenum, *mut GLfloat);
+
+/// Draws a graph of the recent frame times in the bottom left corner of the
+/// window. One bar per frame, 2px per millisecond, red when the frame missed
+/// a display refresh.
+///
+/// Only relies on scissored clears, so no GL program is needed. The state it
+/// touches is restored afterwards, which needs raw glGetIntegerv and
+/// glGetFloatv as gleam only returns the first value of a query.
+pub struct Overlay {
+    get_integerv: GetIntegervFn,
+    get_floatv: GetFloatvFn,
+    /// Whether the draw and read framebuffers are bound separately.
+    separate_framebuffers: bool,
+}
+
+impl Overlay {
+    pub fn load(window: &glutin::GlWindow, separate_framebuffers: bool) -> Option<Overlay> {
+        let get_integerv = window.get_proc_address("glGetIntegerv");
+        let get_floatv = window.get_proc_address("glGetFloatv");
+        if get_integerv.is_null() || get_floatv.is_null() {
+            return None;
+        }
+        Some(Overlay {
+            get_integerv: unsafe { mem::transmute::<_, GetIntegervFn>(get_integerv) },
+            get_floatv: unsafe { mem::transmute::<_, GetFloatvFn>(get_floatv) },
+            separate_framebuffers: separate_framebuffers,
+        })
+    }
+
+    /// The context must be current.
+    pub fn draw(&self, gl: &gl::Gl, stats: &FrameStats, refresh_interval: Duration) {
+        let (target, binding) = if self.separate_framebuffers {
+            (gl::DRAW_FRAMEBUFFER, gl::DRAW_FRAMEBUFFER_BINDING)
+        } else {
+            (gl::FRAMEBUFFER, gl::FRAMEBUFFER_BINDING)
+        };
+        let mut clear_color = [0.; 4];
+        let mut scissor_box = [0; 4];
+        let mut framebuffer = 0;
+        unsafe {
+            (self.get_floatv)(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+            (self.get_integerv)(gl::SCISSOR_BOX, scissor_box.as_mut_ptr());
+            (self.get_integerv)(binding, &mut framebuffer);
+        }
+        // Contexts without framebuffer objects always report 0, so this
+        // doesn't call into a missing glBindFramebuffer.
+        if framebuffer != 0 {
+            gl.bind_framebuffer(target, 0);
+        }
+        let scissor_enabled = gl.is_enabled(gl::SCISSOR_TEST) != 0;
+
+        draw_graph(gl, stats, refresh_interval);
+
+        if !scissor_enabled {
+            gl.disable(gl::SCISSOR_TEST);
+        }
+        gl.scissor(scissor_box[0], scissor_box[1], scissor_box[2], scissor_box[3]);
+        gl.clear_color(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+        if framebuffer != 0 {
+            gl.bind_framebuffer(target, framebuffer as gl::GLuint);
+        }
+    }
+}
+
+fn draw_graph(gl: &gl::Gl, stats: &FrameStats, refresh_interval: Duration) {
+    const PX_PER_MS: f64 = 2.;
+
+    gl.enable(gl::SCISSOR_TEST);
+
+    let fill = |x: i32, y: i32, w: i32, h: i32, color: (f32, f32, f32)| {
+        gl.scissor(x, y, w, h);
+        gl.clear_color(color.0, color.1, color.2, 1.0);
+        gl.clear(gl::COLOR_BUFFER_BIT);
+    };
+
+    fill(0, 0, OVERLAY_SIZE.0, OVERLAY_SIZE.1, (0.1, 0.1, 0.1));
+    let budget_ms = to_ms(refresh_interval);
+    for (i, &frame_time) in stats.frame_times.iter().enumerate() {
+        let frame_ms = to_ms(frame_time);
+        let height = cmp::min((frame_ms * PX_PER_MS) as i32, MAX_HEIGHT);
+        let color = if frame_ms > budget_ms * 1.5 {
+            (0.9, 0.2, 0.2)
+        } else {
+            (0.2, 0.8, 0.3)
+        };
+        fill(i as i32 * BAR_WIDTH, 0, BAR_WIDTH, height, color);
+    }
+    let budget_y = (budget_ms * PX_PER_MS) as i32;
+    fill(0, budget_y, OVERLAY_SIZE.0, 1, (1.0, 1.0, 1.0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn record(frame_times: &[u64]) -> FrameTimings {
+        let mut timings = FrameTimings::new();
+        let mut now = Instant::now();
+        timings.record_swap(now, ms(16));
+        for &frame_time in frame_times {
+            now += ms(frame_time);
+            timings.record_swap(now, ms(16));
+        }
+        timings
+    }
+
+    #[test]
+    fn empty() {
+        let stats = FrameTimings::new().stats();
+        assert!(stats.frame_times.is_empty());
+        assert_eq!(stats.p50, ms(0));
+        assert_eq!(stats.fps, 0.);
+    }
+
+    #[test]
+    fn percentiles() {
+        let frame_times: Vec<u64> = (1..101).collect();
+        let stats = record(&frame_times).stats();
+        assert_eq!(stats.frame_times.len(), 100);
+        assert_eq!(stats.p50, ms(51));
+        assert_eq!(stats.p95, ms(95));
+        assert_eq!(stats.p99, ms(99));
+    }
+
+    #[test]
+    fn dropped_frames() {
+        // On time, one refresh missed, then two.
+        let stats = record(&[16, 32, 48]).stats();
+        assert_eq!(stats.dropped_frames, 3);
+        assert_eq!(stats.fps, 3000. / 96.);
+    }
+
+    #[test]
+    fn idle_gaps_are_ignored() {
+        let stats = record(&[16, 1000, 16]).stats();
+        assert_eq!(stats.frame_times, vec![ms(16), ms(16)]);
+        assert_eq!(stats.dropped_frames, 0);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let frame_times = vec![16; HISTORY_LEN + 10];
+        let stats = record(&frame_times).stats();
+        assert_eq!(stats.frame_times.len(), HISTORY_LEN);
+    }
+}
@@ -0,0 +1,119 @@
+//! Forward KHR_debug messages to the log crate, and glGetError and
+//! extension helpers.
+
+use gleam::gl::{self, GLboolean, GLenum, GLsizei, GLuint};
+use glutin::{self, GlContext};
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::slice;
+
+const DEBUG_OUTPUT: GLenum = 0x92E0;
+const DEBUG_OUTPUT_SYNCHRONOUS: GLenum = 0x8242;
+const DEBUG_SEVERITY_HIGH: GLenum = 0x9146;
+const DEBUG_SEVERITY_MEDIUM: GLenum = 0x9147;
+const DEBUG_SEVERITY_LOW: GLenum = 0x9148;
+const DONT_CARE: GLenum = 0x1100;
+const NUM_EXTENSIONS: GLenum = 0x821D;
+
+/// glGetError can keep returning errors on a lost context.
+const MAX_ERRORS: usize = 16;
+
+type DebugProc = extern "system" fn(GLenum, GLenum, GLuint, GLenum, GLsizei, *const c_char, *mut c_void);
+type DebugMessageCallbackFn = extern "system" fn(DebugProc, *const c_void);
+type DebugMessageControlFn = extern "system" fn(GLenum, GLenum, GLenum, GLsizei, *const GLuint, GLboolean);
+
+pub struct GLDebug {
+    message_control: DebugMessageControlFn,
+}
+
+impl GLDebug {
+    /// Route the debug messages of the current context to the log crate.
+    /// Returns None if KHR_debug is not supported.
+    pub fn enable(window: &glutin::GlWindow, gl: &gl::Gl) -> Option<GLDebug> {
+        if !has_extension(gl, "GL_KHR_debug") {
+            info!("GL_KHR_debug not supported. No GL debug output.");
+            return None;
+        }
+        // Functions are suffixed with KHR in GLES.
+        let load = |name: &str| {
+            [name.to_owned(), format!("{}KHR", name)]
+                .iter()
+                .map(|name| window.get_proc_address(name))
+                .find(|ptr| !ptr.is_null())
+        };
+        let (message_callback, message_control) = match (load("glDebugMessageCallback"),
+                                                          load("glDebugMessageControl")) {
+            (Some(callback), Some(control)) => unsafe {
+                (mem::transmute::<_, DebugMessageCallbackFn>(callback),
+                 mem::transmute::<_, DebugMessageControlFn>(control))
+            },
+            _ => return None,
+        };
+
+        gl.enable(DEBUG_OUTPUT);
+        // Report messages from the offending call, not later.
+        gl.enable(DEBUG_OUTPUT_SYNCHRONOUS);
+        message_callback(debug_callback, ptr::null());
+
+        Some(GLDebug {
+            message_control: message_control,
+        })
+    }
+
+    /// Stop reporting the messages with these ids. The context must be current.
+    pub fn ignore(&self, ids: &[GLuint]) {
+        (self.message_control)(DONT_CARE,
+                               DONT_CARE,
+                               DONT_CARE,
+                               ids.len() as GLsizei,
+                               ids.as_ptr(),
+                               gl::FALSE);
+    }
+}
+
+extern "system" fn debug_callback(_source: GLenum,
+                                  _type: GLenum,
+                                  id: GLuint,
+                                  severity: GLenum,
+                                  length: GLsizei,
+                                  message: *const c_char,
+                                  _user_param: *mut c_void) {
+    let message = unsafe {
+        if length < 0 {
+            CStr::from_ptr(message).to_string_lossy().into_owned()
+        } else {
+            let bytes = slice::from_raw_parts(message as *const u8, length as usize);
+            String::from_utf8_lossy(bytes).into_owned()
+        }
+    };
+    match severity {
+        DEBUG_SEVERITY_HIGH => error!("GL debug ({}): {}", id, message),
+        DEBUG_SEVERITY_MEDIUM => warn!("GL debug ({}): {}", id, message),
+        DEBUG_SEVERITY_LOW => info!("GL debug ({}): {}", id, message),
+        _ => debug!("GL debug ({}): {}", id, message),
+    }
+}
+
+pub fn has_extension(gl: &gl::Gl, extension: &str) -> bool {
+    // GL_EXTENSIONS is not available with glGetString in core profiles.
+    let count = gl.get_integer_v(NUM_EXTENSIONS);
+    if gl.get_error() == gl::NO_ERROR && count > 0 {
+        (0..count as GLuint).any(|i| gl.get_string_i(gl::EXTENSIONS, i) == extension)
+    } else {
+        gl.get_string(gl::EXTENSIONS).split(' ').any(|ext| ext == extension)
+    }
+}
+
+/// Drain the pending glGetError codes.
+pub fn drain_errors(gl: &gl::Gl) -> Vec<GLenum> {
+    let mut errors = vec![];
+    loop {
+        let error = gl.get_error();
+        if error == gl::NO_ERROR || errors.len() == MAX_ERRORS {
+            return errors;
+        }
+        errors.push(error);
+    }
+}
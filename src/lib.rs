@@ -1,8 +1,5 @@
 #![feature(box_syntax)]
 
-#[macro_use]
-extern crate lazy_static;
-
 #[macro_use]
 extern crate log;
 
@@ -14,28 +11,47 @@ extern crate gleam;
 extern crate euclid;
 extern crate winit;
 extern crate servoapi;
+#[cfg(target_os = "linux")]
+extern crate x11_dl;
 
-use euclid::{TypedPoint2D, TypedVector2D};
+mod damage;
+mod error;
+mod error_checking_gl;
+mod frame_stats;
+mod frame_pacer;
+mod gl_debug;
+mod timers;
+mod wake_state;
+mod x11;
+
+use euclid::{Point2D, Rect, Size2D, TypedPoint2D, TypedSideOffsets2D, TypedSize2D, TypedVector2D};
 use gleam::gl;
-use std::rc::Rc;
-use std::collections::HashMap;
+use glutin::GlContext;
+use std::any::Any;
+use std::cmp;
+use std::mem;
+use std::path::PathBuf;
+use std::fmt;
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Condvar, Mutex};
+use std::collections::{HashMap, HashSet, VecDeque};
 use servoapi::{DrawableGeometry, GLMethods, EventLoopWaker, TouchEventType, ScrollLocation};
 use servoapi::{Key, KeyModifiers as ServoKeyModifiers, KeyState};
 use servoapi::{ALT, CONTROL, SHIFT, SUPER};
 use servoapi::WindowEvent as ServoWindowEvent;
 use servoapi::Cursor as ServoCursor;
-use servoapi::{MouseWindowEvent, MouseButton};
+use servoapi::{MouseWindowEvent, MouseButton, TouchId};
 use std::cell::{Cell, RefCell};
-
-lazy_static! {
-    static ref LOOP: glutin::EventsLoop = {
-        glutin::EventsLoop::new()
-    };
-}
-
-thread_local! {
-    static WINDOWS_STATE: RefCell<HashMap<GLWindowId, WindowState>> = RefCell::new(HashMap::new());
-}
+use damage::DamageSwapper;
+use error_checking_gl::ErrorCheckingGl;
+use frame_pacer::{FramePacer, frame_interval};
+use frame_stats::FrameTimings;
+use gl_debug::GLDebug;
+use timers::Timers;
+use wake_state::WakeState;
+use x11::X11Window;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 bitflags! {
     flags KeyModifiers: u8 {
@@ -51,29 +67,310 @@ bitflags! {
 }
 
 pub use glutin::WindowId as GLWindowId;
+pub use error::Error;
+pub use frame_stats::FrameStats;
+pub use timers::TimerId;
+pub use glutin::{PixelFormat, Robustness};
+pub use servoapi::DevicePixel;
 
+type WindowsState = Rc<RefCell<HashMap<GLWindowId, WindowState>>>;
+/// The hidden windows of the headless contexts, whose events are ignored.
+type HeadlessWindows = Rc<RefCell<HashSet<GLWindowId>>>;
+
+/// Device pixels divided by the scale factor. That's what CSS pixels are
+/// at a zoom level of 1.
+#[derive(Clone, Copy, Debug)]
+pub enum LogicalPixel {}
 
 #[derive(Debug)]
 pub struct WindowState {
-    mouse_position: (i32, i32),
+    /// None until the cursor moves in the window, and once it left it.
+    mouse_position: Option<TypedPoint2D<f32, DevicePixel>>,
+    /// Kept after the cursor left the window, for the button release that
+    /// ends a drag outside of it.
+    last_mouse_position: Option<TypedPoint2D<f32, DevicePixel>>,
     key_modifiers: Cell<KeyModifiers>,
     pending_key_event_char: Cell<Option<char>>,
     pressed_key_map: RefCell<Vec<(glutin::ScanCode, char)>>,
+    frame_pacer: FramePacer,
+    refresh_pending: bool,
+    context_lost: bool,
+    /// Outer position.
+    position: Option<(i32, i32)>,
+    inner_size: Option<(u32, u32)>,
+    /// Top, right, bottom, left.
+    margins: (u32, u32, u32, u32),
+    scale_factor: f32,
+    scale_factor_changed: bool,
+    geometry_changed: bool,
+    /// For the cursor changes done from the event loop.
+    window: WeakWindow,
+    x11: Option<Rc<X11Window>>,
+    cursor: glutin::MouseCursor,
+    /// Shown instead of `cursor` if set.
+    custom_cursor: Option<CustomCursor>,
+    cursor_visible: bool,
+    cursor_grab: CursorGrab,
+    pointer_lock_changed: Option<bool>,
+    fullscreen_changed: Option<bool>,
+    /// Content doesn't see the Escape press that unlocked the pointer, so it
+    /// mustn't see the release either.
+    swallow_escape_release: bool,
+    /// glutin sends one event per file. Coalesced into a single DragEnter.
+    hovered_files: Vec<PathBuf>,
+    /// Whether DragEnter got sent, and neither Drop nor DragLeave since.
+    dragging: bool,
+    /// Coalesced into a single Drop.
+    dropped_files: Vec<PathBuf>,
+}
+
+/// glutin windows don't implement Debug.
+struct WeakWindow(Weak<glutin::GlWindow>);
+
+impl fmt::Debug for WeakWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WeakWindow")
+    }
 }
 
 impl WindowState {
-    pub fn glutin_event_to_servo_event(&mut self,
-                                       event: &glutin::WindowEvent)
-                                       -> Option<ServoWindowEvent> {
+    /// Events coalesced while processing the glutin events of an iteration,
+    /// taken one at a time.
+    fn take_pending_event(&mut self) -> Option<GLEvent> {
+        if mem::replace(&mut self.refresh_pending, false) {
+            return Some(GLEvent::Servo(ServoWindowEvent::Refresh));
+        }
+        if mem::replace(&mut self.context_lost, false) {
+            return Some(GLEvent::ContextLost);
+        }
+        if mem::replace(&mut self.scale_factor_changed, false) {
+            return Some(GLEvent::ScaleFactorChanged(self.scale_factor));
+        }
+        if mem::replace(&mut self.geometry_changed, false) {
+            return Some(GLEvent::GeometryChanged);
+        }
+        if let Some(locked) = self.pointer_lock_changed.take() {
+            return Some(GLEvent::PointerLockChanged(locked));
+        }
+        if let Some(fullscreen) = self.fullscreen_changed.take() {
+            return Some(GLEvent::FullscreenChanged(fullscreen));
+        }
+        if !self.hovered_files.is_empty() {
+            let files = mem::replace(&mut self.hovered_files, vec![]);
+            self.dragging = true;
+            return Some(GLEvent::DragEnter(files, self.drag_position()));
+        }
+        if !self.dropped_files.is_empty() {
+            let files = mem::replace(&mut self.dropped_files, vec![]);
+            self.dragging = false;
+            return Some(GLEvent::Drop(files, self.drag_position()));
+        }
+        None
+    }
+
+    fn has_pending_events(&self) -> bool {
+        self.refresh_pending || self.context_lost ||
+        self.scale_factor_changed || self.geometry_changed || self.pointer_lock_changed.is_some() ||
+        self.fullscreen_changed.is_some() || !self.hovered_files.is_empty() || !self.dropped_files.is_empty()
+    }
+
+    /// Keep the input and cursor state of a window replaced by
+    /// `recreate_context`.
+    fn inherit(&mut self, previous: WindowState) {
+        self.mouse_position = previous.mouse_position;
+        self.last_mouse_position = previous.last_mouse_position;
+        self.key_modifiers = previous.key_modifiers;
+        self.pressed_key_map = previous.pressed_key_map;
+        self.frame_pacer.redraw_requested = previous.frame_pacer.redraw_requested;
+        self.cursor = previous.cursor;
+        self.custom_cursor = previous.custom_cursor;
+        self.cursor_visible = previous.cursor_visible;
+        self.cursor_grab = previous.cursor_grab;
+        if let Err(err) = self.update_cursor_state() {
+            warn!("Couldn't restore the cursor state: {}", err);
+        }
+        if self.cursor_grab == CursorGrab::Locked {
+            self.recenter_cursor();
+        }
+    }
+
+    /// The window doesn't get mouse moves while the drag source grabs the
+    /// pointer, at least on X11, so ask where the pointer is.
+    fn drag_position(&self) -> Option<TypedPoint2D<f32, DevicePixel>> {
+        self.x11
+            .as_ref()
+            .and_then(|x11| x11.pointer_position())
+            .map(|(x, y)| TypedPoint2D::new(x as f32, y as f32))
+            .or(self.mouse_position)
+    }
+
+    /// The decorations can change after the window got built, e.g. once the
+    /// X11 window manager reparents the window.
+    fn update_margins(&mut self) {
+        let margins = match self.window.0.upgrade() {
+            Some(window) => compute_margins(&window),
+            None => return,
+        };
+        if margins != self.margins {
+            self.margins = margins;
+            self.geometry_changed = true;
+        }
+    }
+
+    /// glutin only sends HiDPIFactorChanged on some platforms (not on X11),
+    /// so the scale factor is also checked when the window moves or gets
+    /// resized. Asking the window is cheap, unlike going through the
+    /// monitors.
+    fn check_scale_factor(&mut self) {
+        if let Some(window) = self.window.0.upgrade() {
+            self.set_scale_factor(window.hidpi_factor());
+        }
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f32) {
+        if scale_factor != self.scale_factor {
+            self.scale_factor = scale_factor;
+            self.scale_factor_changed = true;
+            self.geometry_changed = true;
+        }
+    }
+
+    fn set_cursor_grab(&mut self, grab: CursorGrab) -> Result<(), Error> {
+        let previous = self.cursor_grab;
+        self.cursor_grab = grab;
+        if let Err(err) = self.update_cursor_state() {
+            self.cursor_grab = previous;
+            return Err(err);
+        }
+        let locked = grab == CursorGrab::Locked;
+        if locked != (previous == CursorGrab::Locked) {
+            self.pointer_lock_changed = Some(locked);
+        }
+        if locked {
+            self.recenter_cursor();
+        }
+        Ok(())
+    }
+
+    fn is_cursor_hidden_by_grab(&self) -> bool {
+        match self.cursor_grab {
+            CursorGrab::None => false,
+            CursorGrab::Confined => !self.cursor_visible,
+            CursorGrab::Locked => true,
+        }
+    }
+
+    /// glutin has a single cursor state, so a grabbed cursor is hidden by
+    /// switching to the invisible cursor instead.
+    fn update_cursor_state(&self) -> Result<(), Error> {
+        let window = match self.window.0.upgrade() {
+            Some(window) => window,
+            None => return Ok(()),
+        };
+        let state = match (self.cursor_grab, self.cursor_visible) {
+            (CursorGrab::None, true) => glutin::CursorState::Normal,
+            (CursorGrab::None, false) => glutin::CursorState::Hide,
+            (CursorGrab::Confined, _) | (CursorGrab::Locked, _) => glutin::CursorState::Grab,
+        };
+        try!(window.set_cursor_state(state).map_err(Error::Platform));
+        if self.is_cursor_hidden_by_grab() {
+            window.set_cursor(glutin::MouseCursor::NoneCursor);
+            return Ok(());
+        }
+        // glutin keeps track of a regular cursor, which the custom one
+        // replaces.
+        window.set_cursor(self.cursor);
+        match (self.custom_cursor.as_ref(), self.x11.as_ref()) {
+            (None, _) => Ok(()),
+            (Some(custom), Some(x11)) if x11.define_cursor(&custom.rgba, custom.size, custom.hotspot) => Ok(()),
+            (Some(_), Some(_)) => Err(Error::Platform("Couldn't create the custom cursor".to_owned())),
+            (Some(_), None) => Err(Error::Unsupported("set_custom_cursor")),
+        }
+    }
+
+    fn cursor_center(&self) -> Option<(i32, i32)> {
+        self.inner_size.map(|(width, height)| (width as i32 / 2, height as i32 / 2))
+    }
+
+    fn recenter_cursor(&self) {
+        if let (Some(window), Some((x, y))) = (self.window.0.upgrade(), self.cursor_center()) {
+            if window.set_cursor_position(x, y).is_err() {
+                warn!("Failed to move the cursor to the center of the window.");
+            }
+        }
+    }
+
+    pub fn glutin_event_to_gl_event(&mut self,
+                                    event: &glutin::WindowEvent)
+                                    -> Option<GLEvent> {
         match *event {
-            glutin::WindowEvent::MouseMoved(x, y) => {
-                self.mouse_position = (x, y);
-                let servo_event =
-                    ServoWindowEvent::MouseWindowMoveEventClass(TypedPoint2D::new(x as f32,
-                                                                                  y as f32));
-                Some(servo_event)
-            }
-            glutin::WindowEvent::MouseWheel(delta, phase) => {
+            glutin::WindowEvent::CursorMoved { position: (x, y), .. } if self.cursor_grab == CursorGrab::Locked => {
+                let (x, y) = (x as i32, y as i32);
+                match self.cursor_center() {
+                    // Echo of our own warp.
+                    Some(center) if center == (x, y) => None,
+                    Some((center_x, center_y)) => {
+                        self.recenter_cursor();
+                        Some(GLEvent::MouseMotion(TypedVector2D::new(x - center_x, y - center_y)))
+                    }
+                    None => None,
+                }
+            }
+            glutin::WindowEvent::CursorMoved { position: (x, y), .. } if self.dragging => {
+                let point = TypedPoint2D::new(x as f32, y as f32);
+                self.mouse_position = Some(point);
+                self.last_mouse_position = Some(point);
+                Some(GLEvent::DragOver(point))
+            }
+            glutin::WindowEvent::CursorMoved { position: (x, y), .. } => {
+                let point = TypedPoint2D::new(x as f32, y as f32);
+                self.mouse_position = Some(point);
+                self.last_mouse_position = Some(point);
+                Some(GLEvent::Servo(ServoWindowEvent::MouseWindowMoveEventClass(point)))
+            }
+            glutin::WindowEvent::HoveredFile(ref path) => {
+                self.hovered_files.push(path.clone());
+                None
+            }
+            glutin::WindowEvent::HoveredFileCancelled => {
+                // Nothing to cancel if DragEnter hasn't been sent yet.
+                self.hovered_files.clear();
+                if mem::replace(&mut self.dragging, false) {
+                    Some(GLEvent::DragLeave)
+                } else {
+                    None
+                }
+            }
+            glutin::WindowEvent::DroppedFile(ref path) => {
+                self.dropped_files.push(path.clone());
+                None
+            }
+            glutin::WindowEvent::Touch(touch) => {
+                let (x, y) = touch.location;
+                let point = TypedPoint2D::new(x as f32, y as f32);
+                let phase = glutin_phase_to_touch_event_type(touch.phase);
+                Some(GLEvent::Servo(ServoWindowEvent::Touch(phase, TouchId(touch.id as i32), point)))
+            }
+            glutin::WindowEvent::CursorEntered { .. } => Some(GLEvent::MouseEntered),
+            glutin::WindowEvent::CursorLeft { .. } => {
+                self.mouse_position = None;
+                Some(GLEvent::MouseLeft)
+            }
+            glutin::WindowEvent::Resized(width, height) => {
+                self.inner_size = Some((width, height));
+                self.geometry_changed = true;
+                Some(GLEvent::Servo(ServoWindowEvent::Resize(TypedSize2D::new(width, height))))
+            }
+            glutin::WindowEvent::HiDPIFactorChanged(scale_factor) => {
+                self.set_scale_factor(scale_factor);
+                None
+            }
+            glutin::WindowEvent::Moved(x, y) => {
+                self.position = Some((x, y));
+                self.geometry_changed = true;
+                Some(GLEvent::Moved(TypedPoint2D::new(x, y)))
+            }
+            glutin::WindowEvent::MouseWheel { delta, phase, .. } => {
                 let (mut dx, mut dy) = match delta {
                     // FIXME: magic value
                     glutin::MouseScrollDelta::LineDelta(dx, dy) => (dx, dy * 38.),
@@ -85,19 +382,18 @@ impl WindowState {
                     dy = 0.0;
                 }
                 let scroll_location = ScrollLocation::Delta(TypedVector2D::new(dx, dy));
-                let phase = match phase {
-                    glutin::TouchPhase::Started => TouchEventType::Down,
-                    glutin::TouchPhase::Moved => TouchEventType::Move,
-                    glutin::TouchPhase::Ended => TouchEventType::Up,
-                    glutin::TouchPhase::Cancelled => TouchEventType::Cancel,
-                };
-                let (x, y) = self.mouse_position;
-                Some(ServoWindowEvent::Scroll(scroll_location, TypedPoint2D::new(x, y), phase))
+                let phase = glutin_phase_to_touch_event_type(phase);
+                self.mouse_position.map(|point| {
+                    let point = TypedPoint2D::new(point.x.round() as i32, point.y.round() as i32);
+                    GLEvent::Servo(ServoWindowEvent::Scroll(scroll_location, point, phase))
+                })
             }
-            glutin::WindowEvent::MouseInput(glutin::ElementState::Released, glutin::MouseButton::Left) => {
-                let (x, y) = self.mouse_position;
-                let mouse_event = MouseWindowEvent::Click(MouseButton::Left, TypedPoint2D::new(x as f32, y as f32));
-                Some(ServoWindowEvent::MouseWindowEventClass(mouse_event))
+            glutin::WindowEvent::MouseInput { state: glutin::ElementState::Released,
+                                              button: glutin::MouseButton::Left, .. } => {
+                self.last_mouse_position.map(|point| {
+                    let mouse_event = MouseWindowEvent::Click(MouseButton::Left, point);
+                    GLEvent::Servo(ServoWindowEvent::MouseWindowEventClass(mouse_event))
+                })
             }
             glutin::WindowEvent::ReceivedCharacter(ch) => {
                 if !ch.is_control() {
@@ -105,7 +401,48 @@ impl WindowState {
                 }
                 None
             }
-            glutin::WindowEvent::KeyboardInput(element_state, scan_code, Some(virtual_key_code), _mods) => {
+            glutin::WindowEvent::KeyboardInput {
+                input: glutin::KeyboardInput {
+                    state: glutin::ElementState::Pressed,
+                    virtual_keycode: Some(glutin::VirtualKeyCode::Escape),
+                    ..
+                },
+                ..
+            } if self.cursor_grab == CursorGrab::Locked => {
+                // Escape hatch, content can't keep the pointer locked.
+                if let Err(err) = self.set_cursor_grab(CursorGrab::None) {
+                    warn!("Failed to unlock the pointer: {}", err);
+                }
+                self.swallow_escape_release = true;
+                None
+            }
+            glutin::WindowEvent::KeyboardInput {
+                input: glutin::KeyboardInput {
+                    state: glutin::ElementState::Released,
+                    virtual_keycode: Some(glutin::VirtualKeyCode::Escape),
+                    ..
+                },
+                ..
+            } if self.swallow_escape_release => {
+                self.swallow_escape_release = false;
+                None
+            }
+            glutin::WindowEvent::Focused(false) if self.cursor_grab == CursorGrab::Locked => {
+                // Don't keep the pointer of other windows locked.
+                if let Err(err) = self.set_cursor_grab(CursorGrab::None) {
+                    warn!("Failed to unlock the pointer: {}", err);
+                }
+                None
+            }
+            glutin::WindowEvent::KeyboardInput {
+                input: glutin::KeyboardInput {
+                    state: element_state,
+                    scancode: scan_code,
+                    virtual_keycode: Some(virtual_key_code),
+                    ..
+                },
+                ..
+            } => {
 
 
                 let m = match virtual_key_code {
@@ -154,160 +491,1555 @@ impl WindowState {
                         glutin::ElementState::Released => KeyState::Released,
                     };
                     let modifiers = glutin_mods_to_script_mods(self.key_modifiers.get());
-                    Some(ServoWindowEvent::KeyEvent(ch, key, state, modifiers))
+                    Some(GLEvent::Servo(ServoWindowEvent::KeyEvent(ch, key, state, modifiers)))
                 } else {
                     None
                 }
             }
 
             _ => {
-                None /* FIXME */
+                warn!("Got unknown glutin event: {:?}", event);
+                None
             }
         }
     }
 }
 
-pub fn run<F: FnMut(ServoWindowEvent, Option<GLWindowId>)>(mut callback: F) {
-    loop {
-        LOOP.run_forever(|e| {
-            match e {
-                glutin::Event::WindowEvent {event, window_id} => {
-                    WINDOWS_STATE.with(|windows| {
-                        let mut windows = windows.borrow_mut();
-                        let win_state = windows.get_mut(&window_id);
-                        match win_state {
-                            Some(win_state) => {
-                                match win_state.glutin_event_to_servo_event(&event) {
-                                    Some(servo_event) => callback(servo_event, Some(window_id)),
-                                    None => {
-                                        warn!("Got unknown glutin event: {:?}", event);
-                                    }
-                                }
-                            },
-                            None => {
-                                // Apparently, Awakened comes with GLWindowId(0),
-                                // which is a non existing window
+/// Events sent to Servo use its own device pixel unit. Ours is for the
+/// geometry API of this crate.
+#[derive(Clone, Debug)]
+struct CustomCursor {
+    rgba: Vec<u8>,
+    size: (u32, u32),
+    hotspot: (u32, u32),
+}
+
+/// Payload attached to a wake up with `GLWindowEventLoopWaker::wake_with`.
+pub type WakeReason = Box<Any + Send>;
+
+pub enum GLEvent {
+    /// An event to forward to Servo.
+    Servo(ServoWindowEvent),
+    /// The loop has been woken up with a reason. Sent right before the Idle
+    /// event of that wake up.
+    Woken(WakeReason),
+    /// A timer fired. Carries the token passed to `schedule_at` or
+    /// `schedule_repeating`.
+    Timer(TimerId, u64),
+    /// Time to draw the window. Sent at most once per display refresh,
+    /// after `GLWindow::request_redraw` has been called.
+    Frame,
+    /// The GL context of the window is gone, after a GPU reset or a driver
+    /// update. See `GLWindow::recreate_context`.
+    ContextLost,
+    /// The window moved to a monitor with a different scale factor.
+    ScaleFactorChanged(f32),
+    /// The window moved, got resized, or its scale factor changed. Sent
+    /// once per iteration. See `GLWindow::geometry`.
+    GeometryChanged,
+    /// New position of the top left corner of the window decorations.
+    Moved(TypedPoint2D<i32, DevicePixel>),
+    /// Mouse motion while the pointer is locked, instead of the absolute
+    /// positions. See `CursorGrab::Locked`.
+    MouseMotion(TypedVector2D<i32, DevicePixel>),
+    /// The pointer got locked or unlocked. Pressing Escape unlocks it.
+    PointerLockChanged(bool),
+    /// The window entered or left fullscreen. The new size comes with a
+    /// Resize event.
+    FullscreenChanged(bool),
+    /// The cursor entered the window. The position comes with the next
+    /// `MouseWindowMoveEventClass` event.
+    MouseEntered,
+    /// The cursor left the window. Hover states should be cleared.
+    MouseLeft,
+    /// Files from the desktop are dragged over the window. The position is
+    /// None if the cursor position is not known.
+    DragEnter(Vec<PathBuf>, Option<TypedPoint2D<f32, DevicePixel>>),
+    /// The dragged files moved over the window. Only sent on platforms that
+    /// report mouse moves during a drag, which X11 doesn't.
+    DragOver(TypedPoint2D<f32, DevicePixel>),
+    /// The dragged files got dropped on the window. Ends the drag.
+    Drop(Vec<PathBuf>, Option<TypedPoint2D<f32, DevicePixel>>),
+    /// The dragged files left the window, or the drag got cancelled.
+    DragLeave,
+}
+
+/// What the event loop should do once the callback returns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ControlFlow {
+    /// Keep dispatching events without blocking. Useful while animating.
+    Continue,
+    /// Block until the next event or wake up.
+    Wait,
+    /// Block until the next event, a wake up, or the given instant.
+    WaitUntil(Instant),
+    /// Return from `run`.
+    Exit,
+}
+
+/// Lets wakers and the deadline thread interrupt the loop without owning it.
+/// Interrupting a dropped loop does nothing.
+///
+/// winit forgets the wake ups that arrive right before `run_forever` starts
+/// waiting. So while the loop waits, the deadline thread interrupts it again
+/// until it wakes up, if it missed an interrupt.
+struct LoopInterrupter {
+    proxy: glutin::EventsLoopProxy,
+    state: Mutex<InterruptState>,
+    cvar: Condvar,
+}
+
+struct InterruptState {
+    /// Number of interrupts so far.
+    generation: u64,
+    /// The generation the loop knew about when it started waiting.
+    seen: u64,
+    waiting: bool,
+    deadline: Option<Instant>,
+    /// When to interrupt again if the loop is still waiting.
+    retry_at: Instant,
+    shutdown: bool,
+}
+
+/// How long the loop gets to wake up before being interrupted again.
+fn retry_interval() -> Duration {
+    Duration::from_millis(10)
+}
+
+impl LoopInterrupter {
+    fn new(events_loop: &glutin::EventsLoop) -> LoopInterrupter {
+        LoopInterrupter {
+            proxy: events_loop.create_proxy(),
+            state: Mutex::new(InterruptState {
+                generation: 0,
+                seen: 0,
+                waiting: false,
+                deadline: None,
+                retry_at: Instant::now(),
+                shutdown: false,
+            }),
+            cvar: Condvar::new(),
+        }
+    }
+
+    fn interrupt(&self) {
+        self.interrupt_locked(&mut self.state.lock().unwrap());
+    }
+
+    fn interrupt_locked(&self, state: &mut InterruptState) {
+        state.generation += 1;
+        state.retry_at = Instant::now() + retry_interval();
+        // Fails once the loop is gone, which is fine.
+        let _ = self.proxy.wakeup();
+        self.cvar.notify_one();
+    }
+
+    fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    /// `seen` is the generation read before checking whether the loop got
+    /// woken up. Returns false if it got interrupted since, in which case
+    /// the loop must not wait.
+    fn start_waiting(&self, seen: u64, deadline: Option<Instant>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != seen {
+            return false;
+        }
+        state.seen = seen;
+        state.waiting = true;
+        state.deadline = deadline;
+        self.cvar.notify_one();
+        true
+    }
+
+    fn stop_waiting(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.waiting = false;
+        state.deadline = None;
+    }
+
+    /// Run by the deadline thread until `shutdown` is called.
+    fn watch(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.shutdown {
+            let now = Instant::now();
+            if state.deadline.map_or(false, |deadline| deadline <= now) {
+                state.deadline = None;
+                self.interrupt_locked(&mut state);
+                continue;
+            }
+            let missed = state.waiting && state.generation != state.seen;
+            if missed && state.retry_at <= now {
+                debug!("The event loop missed a wake up. Waking it up again.");
+                state.retry_at = now + retry_interval();
+                let _ = self.proxy.wakeup();
+            }
+            let retry_at = if missed { Some(state.retry_at) } else { None };
+            state = match [state.deadline, retry_at].iter().filter_map(|d| *d).min() {
+                Some(next) => self.cvar.wait_timeout(state, next - now).unwrap().0,
+                None => self.cvar.wait(state).unwrap(),
+            };
+        }
+    }
+
+    fn shutdown(&self) {
+        self.state.lock().unwrap().shutdown = true;
+        self.cvar.notify_one();
+    }
+}
+
+pub struct GLEventLoop {
+    /// Only borrowed mutably while fetching events, so that callbacks can
+    /// create windows.
+    events_loop: RefCell<glutin::EventsLoop>,
+    /// Fetched but not handled yet, because `run` returned first.
+    events: RefCell<VecDeque<glutin::Event>>,
+    interrupter: Arc<LoopInterrupter>,
+    wake_state: Arc<WakeState>,
+    windows_state: WindowsState,
+    headless_windows: HeadlessWindows,
+    /// Only held to stop the thread when the loop is dropped.
+    _deadline_thread: DeadlineThread,
+    timers: RefCell<Timers>,
+}
+
+impl GLEventLoop {
+    pub fn new() -> GLEventLoop {
+        let events_loop = glutin::EventsLoop::new();
+        let interrupter = Arc::new(LoopInterrupter::new(&events_loop));
+        GLEventLoop {
+            _deadline_thread: DeadlineThread::new(interrupter.clone()),
+            events_loop: RefCell::new(events_loop),
+            events: RefCell::new(VecDeque::new()),
+            interrupter: interrupter,
+            wake_state: Arc::new(WakeState::default()),
+            windows_state: Rc::new(RefCell::new(HashMap::new())),
+            headless_windows: Rc::new(RefCell::new(HashSet::new())),
+            timers: RefCell::new(Timers::new()),
+        }
+    }
+
+    pub fn create_window(&self, width: u32, height: u32) -> GLWindow {
+        GLWindowBuilder::new(width, height)
+            .build(self)
+            .expect("Failed to create window.")
+    }
+
+    pub fn create_event_loop_waker(&self) -> Box<GLWindowEventLoopWaker> {
+        box GLWindowEventLoopWaker {
+            interrupter: self.interrupter.clone(),
+            wake_state: self.wake_state.clone(),
+        }
+    }
+
+    /// Fire a `GLEvent::Timer` once `deadline` is reached.
+    pub fn schedule_at(&self, deadline: Instant, token: u64) -> TimerId {
+        self.timers.borrow_mut().add(deadline, None, token)
+    }
+
+    /// Fire a `GLEvent::Timer` every `interval`, until cancelled. Intervals
+    /// shorter than a millisecond are rounded up to one.
+    pub fn schedule_repeating(&self, interval: Duration, token: u64) -> TimerId {
+        self.timers.borrow_mut().add(Instant::now() + interval, Some(interval), token)
+    }
+
+    /// Returns false if the timer already fired or has been cancelled.
+    pub fn cancel(&self, id: TimerId) -> bool {
+        self.timers.borrow_mut().cancel(id)
+    }
+
+    /// When the next timer is due. Useful to embedders that own the main
+    /// loop and call `poll_events`.
+    pub fn next_timer(&self) -> Option<Instant> {
+        self.timers.borrow().next_deadline()
+    }
+
+    /// Dispatch events until the callback returns `ControlFlow::Exit`. The
+    /// last value returned by the callback decides how the loop waits for
+    /// the next events.
+    ///
+    /// Idle is sent once per wake up, once the deadline of a
+    /// `ControlFlow::WaitUntil` is reached, and on every iteration while
+    /// the callback returns `ControlFlow::Continue`.
+    pub fn run<F>(&self, mut callback: F)
+        where F: FnMut(GLEvent, Option<GLWindowId>) -> ControlFlow
+    {
+        let control_flow = Cell::new(ControlFlow::Wait);
+        loop {
+            let current = control_flow.get();
+            // Read before checking `woken`, see `LoopInterrupter::start_waiting`.
+            let generation = self.interrupter.generation();
+            let wait_until = match current {
+                ControlFlow::WaitUntil(instant) => Some(instant),
+                _ => None,
+            };
+            let deadline = [wait_until, self.next_timer(), self.next_frame()].iter().filter_map(|d| *d).min();
+            let blocking = match current {
+                ControlFlow::Exit => return,
+                ControlFlow::Continue => false,
+                // Wake ups and events coalesced while the callback ran are
+                // handled right away.
+                ControlFlow::Wait | ControlFlow::WaitUntil(_) => {
+                    !self.wake_state.is_woken() &&
+                    !self.has_pending_events() &&
+                    deadline.map_or(true, |deadline| deadline > Instant::now())
+                }
+            };
+
+            let blocking = blocking && self.interrupter.start_waiting(generation, deadline);
+            self.fetch_events(blocking);
+            if blocking {
+                self.interrupter.stop_waiting();
+            }
+
+            let timed_out = match current {
+                ControlFlow::Continue => true,
+                ControlFlow::WaitUntil(instant) => instant <= Instant::now(),
+                _ => false,
+            };
+            // Stop as soon as the callback asks to, leaving whatever is left
+            // (events, frames, timers, wake ups) for the next call.
+            let mut dispatch = |event, window_id| {
+                control_flow.set(callback(event, window_id));
+                control_flow.get() != ControlFlow::Exit
+            };
+            let done = self.dispatch_events(&mut dispatch) &&
+                       self.dispatch_pending_events(&mut dispatch) &&
+                       self.dispatch_frames(&mut dispatch) &&
+                       self.fire_timers(&mut dispatch) &&
+                       self.dispatch_idle(&mut dispatch, timed_out);
+            if !done {
+                return;
+            }
+        }
+    }
+
+    /// Like `run`, but returns as soon as `predicate` returns true. The predicate
+    /// is checked after every event.
+    pub fn run_until<F, P>(&self, mut callback: F, mut predicate: P)
+        where F: FnMut(GLEvent, Option<GLWindowId>),
+              P: FnMut() -> bool
+    {
+        self.run(|event, window_id| {
+            callback(event, window_id);
+            if predicate() {
+                ControlFlow::Exit
+            } else {
+                ControlFlow::Wait
+            }
+        })
+    }
+
+    /// Dispatch all the pending events and return immediately. Meant for
+    /// embedders that own the main loop.
+    pub fn poll_events<F: FnMut(GLEvent, Option<GLWindowId>)>(&self, mut callback: F) {
+        let mut dispatch = |event, window_id| {
+            callback(event, window_id);
+            true
+        };
+        self.fetch_events(false);
+        self.dispatch_events(&mut dispatch);
+        self.dispatch_pending_events(&mut dispatch);
+        self.dispatch_frames(&mut dispatch);
+        self.fire_timers(&mut dispatch);
+        self.dispatch_idle(&mut dispatch, false);
+    }
+
+    pub fn monitors(&self) -> Vec<Monitor> {
+        self.events_loop.borrow().get_available_monitors().map(Monitor::new).collect()
+    }
+
+    pub fn primary_monitor(&self) -> Monitor {
+        Monitor::new(self.events_loop.borrow().get_primary_monitor())
+    }
+
+    fn has_pending_events(&self) -> bool {
+        !self.events.borrow().is_empty() ||
+        self.windows_state.borrow().values().any(|state| state.has_pending_events())
+    }
+
+    /// The glutin events are only handled once the loop isn't borrowed
+    /// anymore. If `block` is set, wait for at least one.
+    fn fetch_events(&self, block: bool) {
+        let mut events = self.events.borrow_mut();
+        let mut events_loop = self.events_loop.borrow_mut();
+        if block {
+            events_loop.run_forever(|e| {
+                events.push_back(e);
+                glutin::ControlFlow::Break
+            });
+        }
+        events_loop.poll_events(|e| events.push_back(e));
+    }
+
+    /// The dispatch functions below return false as soon as the callback
+    /// does, without consuming anything else.
+    fn dispatch_events<F: FnMut(GLEvent, Option<GLWindowId>) -> bool>(&self, callback: &mut F) -> bool {
+        loop {
+            let event = self.events.borrow_mut().pop_front();
+            match event {
+                Some(event) => if !self.handle_event(event, callback) {
+                    return false;
+                },
+                None => return true,
+            }
+        }
+    }
+
+    fn dispatch_pending_events<F: FnMut(GLEvent, Option<GLWindowId>) -> bool>(&self, callback: &mut F) -> bool {
+        loop {
+            let pending = self.windows_state
+                              .borrow_mut()
+                              .iter_mut()
+                              .filter_map(|(id, state)| state.take_pending_event().map(|event| (event, *id)))
+                              .next();
+            match pending {
+                Some((event, id)) => if !callback(event, Some(id)) {
+                    return false;
+                },
+                None => return true,
+            }
+        }
+    }
+
+    fn next_frame(&self) -> Option<Instant> {
+        self.windows_state
+            .borrow()
+            .values()
+            .filter_map(|state| state.frame_pacer.next_frame())
+            .min()
+    }
+
+    fn dispatch_frames<F: FnMut(GLEvent, Option<GLWindowId>) -> bool>(&self, callback: &mut F) -> bool {
+        let now = Instant::now();
+        loop {
+            let due = self.windows_state
+                          .borrow_mut()
+                          .iter_mut()
+                          .find(|entry| entry.1.frame_pacer.next_frame().map_or(false, |frame| frame <= now))
+                          .map(|(id, state)| {
+                              state.frame_pacer.on_frame(now);
+                              *id
+                          });
+            match due {
+                Some(id) => if !callback(GLEvent::Frame, Some(id)) {
+                    return false;
+                },
+                None => return true,
+            }
+        }
+    }
+
+    fn fire_timers<F: FnMut(GLEvent, Option<GLWindowId>) -> bool>(&self, callback: &mut F) -> bool {
+        let now = Instant::now();
+        loop {
+            // The callback might schedule or cancel timers.
+            let fired = self.timers.borrow_mut().take_next_due(now);
+            match fired {
+                Some((id, token)) => if !callback(GLEvent::Timer(id, token), None) {
+                    return false;
+                },
+                None => return true,
+            }
+        }
+    }
+
+    /// Send the pending wake reasons, followed by a single Idle event if
+    /// the loop has been woken up since the last call (or if `force` is set).
+    fn dispatch_idle<F: FnMut(GLEvent, Option<GLWindowId>) -> bool>(&self, callback: &mut F, force: bool) -> bool {
+        let woken = self.wake_state.take_woken();
+        while let Some(reason) = self.wake_state.pop_reason() {
+            if !callback(GLEvent::Woken(reason), None) {
+                // The remaining reasons and the Idle event are sent by the
+                // next call.
+                self.wake_state.wake(None);
+                return false;
+            }
+        }
+        if woken || force {
+            return callback(GLEvent::Servo(ServoWindowEvent::Idle), None);
+        }
+        true
+    }
+
+    fn handle_event<F: FnMut(GLEvent, Option<GLWindowId>) -> bool>(&self,
+                                                                   event: glutin::Event,
+                                                                   callback: &mut F)
+                                                                   -> bool {
+        match event {
+            glutin::Event::WindowEvent {event, window_id} => {
+                // The registry must not be borrowed while the callback runs,
+                // as the callback might create or drop windows.
+                let gl_event = {
+                    let mut windows = self.windows_state.borrow_mut();
+                    match windows.get_mut(&window_id) {
+                        Some(win_state) => {
+                            if let glutin::WindowEvent::Refresh = event {
+                                // Coalesced, see dispatch_pending_events.
+                                win_state.refresh_pending = true;
+                                None
+                            } else {
+                                let gl_event = win_state.glutin_event_to_gl_event(&event);
                                 match event {
-                                    glutin::WindowEvent::Awakened => {
-                                        // FIXME: it's surprising that we have Awakened + the interrupt.
-                                        // Idle is sent twice.
-                                        callback(ServoWindowEvent::Idle, None);
-                                    }
-                                    _ => {
-                                        warn!("Unexpected event ({:?} for unknown Windows ({:?})", event, window_id);
+                                    glutin::WindowEvent::Moved(..) |
+                                    glutin::WindowEvent::Resized(..) => {
+                                        win_state.check_scale_factor();
+                                        win_state.update_margins();
                                     }
+                                    _ => {}
                                 }
-
+                                gl_event
+                            }
+                        },
+                        None => {
+                            if !self.headless_windows.borrow().contains(&window_id) {
+                                warn!("Unexpected event ({:?} for unknown Windows ({:?})", event, window_id);
                             }
+                            None
                         }
-                    });
+                    }
+                };
+                match gl_event {
+                    Some(gl_event) => callback(gl_event, Some(window_id)),
+                    None => true,
                 }
             }
-        });
-        callback(ServoWindowEvent::Idle, None);
+            // Idle is sent by dispatch_idle, once all events are processed.
+            glutin::Event::Awakened => true,
+            glutin::Event::DeviceEvent { .. } | glutin::Event::Suspended(..) => true,
+        }
+    }
+}
+
+/// Interrupts the event loop once a deadline is reached, so that
+/// `ControlFlow::WaitUntil` doesn't block forever, and again when the loop
+/// missed an interrupt.
+struct DeadlineThread {
+    interrupter: Arc<LoopInterrupter>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DeadlineThread {
+    fn new(interrupter: Arc<LoopInterrupter>) -> DeadlineThread {
+        let thread_interrupter = interrupter.clone();
+        DeadlineThread {
+            interrupter: interrupter,
+            thread: Some(thread::spawn(move || thread_interrupter.watch())),
+        }
+    }
+}
+
+impl Drop for DeadlineThread {
+    fn drop(&mut self) {
+        self.interrupter.shutdown();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GLApi {
+    OpenGl,
+    OpenGlEs,
+}
+
+/// A context API and version, as requested or as obtained.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GLContextVersion {
+    pub api: GLApi,
+    pub version: (u8, u8),
+}
+
+impl GLContextVersion {
+    fn to_glutin_request(&self) -> glutin::GlRequest {
+        let api = match self.api {
+            GLApi::OpenGl => glutin::Api::OpenGl,
+            GLApi::OpenGlEs => glutin::Api::OpenGlEs,
+        };
+        glutin::GlRequest::Specific(api, self.version)
+    }
+
+    /// Core profiles only exist since OpenGL 3.2.
+    fn is_core_profile(&self) -> bool {
+        self.api == GLApi::OpenGl && self.version >= (3, 2)
+    }
+}
+
+/// OpenGL 3.2 core, then OpenGL 2.1, then OpenGL ES 3.0, then OpenGL ES 2.0.
+fn default_gl_preferences() -> Vec<GLContextVersion> {
+    vec![
+        GLContextVersion { api: GLApi::OpenGl, version: (3, 2) },
+        GLContextVersion { api: GLApi::OpenGl, version: (2, 1) },
+        GLContextVersion { api: GLApi::OpenGlEs, version: (3, 0) },
+        GLContextVersion { api: GLApi::OpenGlEs, version: (2, 0) },
+    ]
+}
+
+/// Kept around by windows, to rebuild their context after it got lost.
+#[derive(Clone)]
+struct ContextAttributes {
+    multisampling: Option<u16>,
+    depth_bits: Option<u8>,
+    stencil_bits: Option<u8>,
+    srgb: bool,
+    robustness: Robustness,
+    gl_debug: bool,
+    /// Whether the context got built with `with_shared_lists`.
+    shared_lists: bool,
+    /// The window state is kept up to date too, so that a rebuilt window
+    /// looks the same.
+    min_size: Option<(u32, u32)>,
+    max_size: Option<(u32, u32)>,
+    maximized: bool,
+    fullscreen: Option<glutin::MonitorId>,
+}
+
+impl ContextAttributes {
+    fn lose_context_on_reset(&self) -> bool {
+        match self.robustness {
+            Robustness::RobustLoseContextOnReset |
+            Robustness::TryRobustLoseContextOnReset => true,
+            _ => false,
+        }
+    }
+}
+
+pub struct GLWindowBuilder<'a> {
+    width: u32,
+    height: u32,
+    gl_preferences: Vec<GLContextVersion>,
+    attributes: ContextAttributes,
+    shared_lists: Option<&'a GLWindow>,
+}
+
+impl<'a> GLWindowBuilder<'a> {
+    pub fn new(width: u32, height: u32) -> GLWindowBuilder<'a> {
+        GLWindowBuilder {
+            width: width,
+            height: height,
+            gl_preferences: default_gl_preferences(),
+            attributes: ContextAttributes {
+                multisampling: None,
+                depth_bits: None,
+                stencil_bits: None,
+                srgb: false,
+                robustness: Robustness::NotRobust,
+                gl_debug: cfg!(debug_assertions),
+                shared_lists: false,
+                min_size: None,
+                max_size: None,
+                maximized: false,
+                fullscreen: None,
+            },
+            shared_lists: None,
+        }
+    }
+
+    /// Context versions to try, in order of preference.
+    pub fn with_gl_preferences(mut self, preferences: Vec<GLContextVersion>) -> GLWindowBuilder<'a> {
+        self.gl_preferences = preferences;
+        self
+    }
+
+    /// Number of samples per pixel. Must be a power of 2.
+    pub fn with_multisampling(mut self, samples: u16) -> GLWindowBuilder<'a> {
+        self.attributes.multisampling = Some(samples);
+        self
+    }
+
+    pub fn with_depth_buffer(mut self, bits: u8) -> GLWindowBuilder<'a> {
+        self.attributes.depth_bits = Some(bits);
+        self
+    }
+
+    pub fn with_stencil_buffer(mut self, bits: u8) -> GLWindowBuilder<'a> {
+        self.attributes.stencil_bits = Some(bits);
+        self
+    }
+
+    pub fn with_srgb(mut self, srgb: bool) -> GLWindowBuilder<'a> {
+        self.attributes.srgb = srgb;
+        self
+    }
+
+    /// Request a context with reset notification. See glutin's
+    /// `Robustness` for the available behaviors.
+    pub fn with_robustness(mut self, robustness: Robustness) -> GLWindowBuilder<'a> {
+        self.attributes.robustness = robustness;
+        self
+    }
+
+    /// Debug contexts forward their KHR_debug messages to the log crate.
+    /// Enabled by default in debug builds.
+    pub fn with_gl_debug_flag(mut self, debug: bool) -> GLWindowBuilder<'a> {
+        self.attributes.gl_debug = debug;
+        self
+    }
+
+    pub fn with_min_size(mut self, width: u32, height: u32) -> GLWindowBuilder<'a> {
+        self.attributes.min_size = Some((width, height));
+        self
+    }
+
+    pub fn with_max_size(mut self, width: u32, height: u32) -> GLWindowBuilder<'a> {
+        self.attributes.max_size = Some((width, height));
+        self
+    }
+
+    pub fn with_maximized(mut self, maximized: bool) -> GLWindowBuilder<'a> {
+        self.attributes.maximized = maximized;
+        self
+    }
+
+    /// Borderless fullscreen on `monitor`.
+    pub fn with_fullscreen(mut self, monitor: &Monitor) -> GLWindowBuilder<'a> {
+        self.attributes.fullscreen = Some(monitor.id.clone());
+        self
+    }
+
+    /// Share textures, buffers and shaders with the context of `window`.
+    /// Sharing requires the same API and version, so this replaces the
+    /// context preferences.
+    pub fn with_shared_lists(mut self, window: &'a GLWindow) -> GLWindowBuilder<'a> {
+        self.gl_preferences = vec![window.gl_version()];
+        self.attributes.shared_lists = true;
+        self.shared_lists = Some(window);
+        self
+    }
+
+    /// Try each preferred context version until one works. Returns the
+    /// error of the last attempt if none does.
+    ///
+    /// The pixel format that got picked is available via
+    /// `GLWindow::pixel_format`.
+    pub fn build(self, event_loop: &GLEventLoop) -> Result<GLWindow, Error> {
+        let (glutin_window, version) = try!(self.build_glutin_window(event_loop, true));
+        GLWindow::new(event_loop, glutin_window, version, self.attributes)
+    }
+
+    /// Build an offscreen context, backed by a hidden window. Mostly useful
+    /// along with `with_shared_lists`.
+    pub fn build_headless(self, event_loop: &GLEventLoop) -> Result<GLHeadlessContext, Error> {
+        let (glutin_window, version) = try!(self.build_glutin_window(event_loop, false));
+        let gl = try!(load_gl(&glutin_window, version));
+        event_loop.headless_windows.borrow_mut().insert(glutin_window.id());
+        Ok(GLHeadlessContext {
+            gl: gl,
+            gl_version: version,
+            glutin_window: glutin_window,
+            headless_windows: event_loop.headless_windows.clone(),
+        })
+    }
+
+    fn build_glutin_window(&self,
+                           event_loop: &GLEventLoop,
+                           visible: bool)
+                           -> Result<(glutin::GlWindow, GLContextVersion), glutin::CreationError> {
+        let mut last_error = glutin::CreationError::OpenGlVersionNotSupported;
+        for version in &self.gl_preferences {
+            let window_builder = self.window_builder().with_visibility(visible);
+            let context_builder = self.context_builder(version);
+            match glutin::GlWindow::new(window_builder, context_builder, &event_loop.events_loop.borrow()) {
+                Ok(glutin_window) => return Ok((glutin_window, *version)),
+                Err(err) => {
+                    info!("Couldn't create a {:?} context: {}", version, err);
+                    last_error = err;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    fn window_builder(&self) -> glutin::WindowBuilder {
+        let mut builder = glutin::WindowBuilder::new()
+            .with_dimensions(self.width, self.height)
+            .with_maximized(self.attributes.maximized)
+            .with_fullscreen(self.attributes.fullscreen.clone());
+        if let Some((width, height)) = self.attributes.min_size {
+            builder = builder.with_min_dimensions(width, height);
+        }
+        if let Some((width, height)) = self.attributes.max_size {
+            builder = builder.with_max_dimensions(width, height);
+        }
+        builder
+    }
+
+    fn context_builder(&self, version: &GLContextVersion) -> glutin::ContextBuilder<'a> {
+        let mut builder = glutin::ContextBuilder::new()
+            .with_gl(version.to_glutin_request())
+            .with_gl_robustness(self.attributes.robustness)
+            .with_gl_debug_flag(self.attributes.gl_debug)
+            .with_srgb(self.attributes.srgb)
+            .with_vsync(true);
+        if version.is_core_profile() {
+            builder = builder.with_gl_profile(glutin::GlProfile::Core);
+        }
+        if let Some(window) = self.shared_lists {
+            builder = builder.with_shared_lists(window.glutin_window.context());
+        }
+        if let Some(samples) = self.attributes.multisampling {
+            builder = builder.with_multisampling(samples);
+        }
+        if let Some(bits) = self.attributes.depth_bits {
+            builder = builder.with_depth_buffer(bits);
+        }
+        if let Some(bits) = self.attributes.stencil_bits {
+            builder = builder.with_stencil_buffer(bits);
+        }
+        builder
     }
 }
 
 pub struct GLWindow {
     gl: Rc<gl::Gl>,
-    glutin_window: glutin::Window,
+    gl_version: GLContextVersion,
+    attributes: RefCell<ContextAttributes>,
+    reset_status: Option<GetGraphicsResetStatusFn>,
+    glutin_window: Rc<glutin::GlWindow>,
+    /// For the window management requests glutin doesn't support.
+    x11: Option<Rc<X11Window>>,
+    title: RefCell<String>,
+    windows_state: WindowsState,
+    frame_timings: RefCell<FrameTimings>,
+    frame_stats_overlay: Cell<bool>,
+    overlay: Option<frame_stats::Overlay>,
+    damage_swapper: Option<DamageSwapper>,
+    gl_debug: Option<GLDebug>,
+    gl_error_checking: Cell<bool>,
+    /// Wraps `gl`. Handed out instead of it when error checking is enabled.
+    checked_gl: Rc<gl::Gl>,
+    /// Kept for `recreate_context`.
+    visible: Cell<bool>,
+    /// Outer position and inner size to restore when leaving fullscreen.
+    windowed_geometry: Cell<Option<((i32, i32), (u32, u32))>>,
+}
+
+#[derive(Clone)]
+pub struct Monitor {
+    pub name: Option<String>,
+    /// Top left corner, in screen coordinates.
+    pub position: TypedPoint2D<i32, DevicePixel>,
+    pub size: TypedSize2D<u32, DevicePixel>,
+    pub scale_factor: f32,
+    id: glutin::MonitorId,
+}
+
+impl fmt::Debug for Monitor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Monitor")
+            .field("name", &self.name)
+            .field("position", &self.position)
+            .field("size", &self.size)
+            .field("scale_factor", &self.scale_factor)
+            .finish()
+    }
+}
+
+impl Monitor {
+    fn new(id: glutin::MonitorId) -> Monitor {
+        let (x, y) = id.get_position();
+        let (width, height) = id.get_dimensions();
+        Monitor {
+            name: id.get_name(),
+            position: TypedPoint2D::new(x, y),
+            size: TypedSize2D::new(width, height),
+            scale_factor: id.get_hidpi_factor(),
+            id: id,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WindowGeometry {
+    /// Top left corner of the drawable area, in screen coordinates.
+    pub inner_position: TypedPoint2D<i32, DevicePixel>,
+    pub inner_size: TypedSize2D<u32, DevicePixel>,
+    /// Top left corner of the decorations, in screen coordinates.
+    pub outer_position: TypedPoint2D<i32, DevicePixel>,
+    pub outer_size: TypedSize2D<u32, DevicePixel>,
+    /// Thickness of the decorations around the drawable area. This is a
+    /// guess: glutin only reports the inner and outer sizes, so the side and
+    /// bottom borders are assumed to be as thick, and the rest is assumed to
+    /// be the title bar.
+    pub margins: TypedSideOffsets2D<u32, DevicePixel>,
+    pub scale_factor: f32,
+}
+
+impl WindowGeometry {
+    pub fn logical_inner_size(&self) -> TypedSize2D<f32, LogicalPixel> {
+        TypedSize2D::new(self.inner_size.width as f32 / self.scale_factor,
+                         self.inner_size.height as f32 / self.scale_factor)
+    }
+
+    pub fn logical_inner_position(&self) -> TypedPoint2D<f32, LogicalPixel> {
+        TypedPoint2D::new(self.inner_position.x as f32 / self.scale_factor,
+                          self.inner_position.y as f32 / self.scale_factor)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorGrab {
+    None,
+    /// The cursor can't leave the window.
+    Confined,
+    /// The cursor is confined, hidden and kept at the center of the window,
+    /// for the Pointer Lock API.
+    Locked,
 }
 
 impl GLMethods for GLWindow {
     fn swap_buffers(&self) {
-        self.glutin_window.swap_buffers().unwrap();
+        if let Err(err) = self.try_swap_buffers() {
+            error!("Failed to swap buffers: {}", err);
+        }
     }
     fn make_current(&self) -> Result<(),()> {
-        unsafe {
-            self.glutin_window.make_current().map_err(|_| ())
-        }
+        self.try_make_current().map_err(|err| {
+            error!("Failed to make the context current: {}", err);
+        })
     }
     fn get_gl(&self) -> Rc<gl::Gl> {
-        self.gl.clone()
+        if self.gl_error_checking.get() {
+            self.checked_gl.clone()
+        } else {
+            self.gl.clone()
+        }
     }
 }
 
 impl GLWindow {
-    pub fn new(width: u32, height: u32) -> GLWindow {
-        let glutin_window = glutin::WindowBuilder::new()
-            .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
-            .with_dimensions(width, height)
-            .with_vsync()
-            .build(&LOOP)
-            .expect("Failed to create window.");
-
-        let gl = unsafe {
-            glutin_window
-                .make_current()
-                .expect("Couldn't make window current");
-            gl::GlFns::load_with(|s| glutin_window.get_proc_address(s) as *const _)
+    fn new(event_loop: &GLEventLoop,
+           glutin_window: glutin::GlWindow,
+           gl_version: GLContextVersion,
+           attributes: ContextAttributes)
+           -> Result<GLWindow, Error> {
+        let gl = try!(load_gl(&glutin_window, gl_version));
+
+        let damage_swapper = DamageSwapper::load(&glutin_window);
+        // OpenGL 3.0 and OpenGL ES 3.0 both split the framebuffer binding.
+        let overlay = frame_stats::Overlay::load(&glutin_window, gl_version.version >= (3, 0));
+        let gl_debug = if attributes.gl_debug {
+            GLDebug::enable(&glutin_window, &*gl)
+        } else {
+            None
+        };
+        let reset_status = if attributes.lose_context_on_reset() {
+            load_reset_status(&glutin_window, &*gl)
+        } else {
+            None
         };
 
         gl.clear_color(1.0, 1.0, 1.0, 1.0);
         gl.clear(gleam::gl::COLOR_BUFFER_BIT);
         gl.finish();
 
-        WINDOWS_STATE.with(|windows| {
-                               windows
-                                   .borrow_mut()
-                                   .insert(glutin_window.id(),
-                                           WindowState {
-                                               key_modifiers: Cell::new(KeyModifiers::empty()),
-                                               mouse_position: (0, 0),
-                                               pending_key_event_char: Cell::new(None),
-                                               pressed_key_map: RefCell::new(vec![]),
-                                           });
-                           });
-
-        GLWindow {
+        let checked_gl = Rc::new(ErrorCheckingGl::new(gl.clone()));
+        let x11 = X11Window::load(&glutin_window).map(Rc::new);
+        let glutin_window = Rc::new(glutin_window);
+        event_loop.windows_state
+            .borrow_mut()
+            .insert(glutin_window.id(),
+                    WindowState {
+                        key_modifiers: Cell::new(KeyModifiers::empty()),
+                        mouse_position: None,
+                        last_mouse_position: None,
+                        pending_key_event_char: Cell::new(None),
+                        pressed_key_map: RefCell::new(vec![]),
+                        frame_pacer: FramePacer::new(true),
+                        refresh_pending: false,
+                        context_lost: false,
+                        position: glutin_window.get_position(),
+                        inner_size: glutin_window.get_inner_size(),
+                        margins: compute_margins(&glutin_window),
+                        scale_factor: glutin_window.hidpi_factor(),
+                        scale_factor_changed: false,
+                        geometry_changed: false,
+                        window: WeakWindow(Rc::downgrade(&glutin_window)),
+                        x11: x11.clone(),
+                        cursor: glutin::MouseCursor::Default,
+                        custom_cursor: None,
+                        cursor_visible: true,
+                        cursor_grab: CursorGrab::None,
+                        pointer_lock_changed: None,
+                        fullscreen_changed: None,
+                        swallow_escape_release: false,
+                        hovered_files: vec![],
+                        dragging: false,
+                        dropped_files: vec![],
+                    });
+
+        Ok(GLWindow {
             glutin_window: glutin_window,
+            x11: x11,
             gl: gl,
-        }
+            gl_version: gl_version,
+            attributes: RefCell::new(attributes),
+            reset_status: reset_status,
+            title: RefCell::new(String::new()),
+            windows_state: event_loop.windows_state.clone(),
+            frame_timings: RefCell::new(FrameTimings::new()),
+            frame_stats_overlay: Cell::new(false),
+            overlay: overlay,
+            damage_swapper: damage_swapper,
+            gl_debug: gl_debug,
+            gl_error_checking: Cell::new(false),
+            checked_gl: checked_gl,
+            visible: Cell::new(true),
+            windowed_geometry: Cell::new(None),
+        })
     }
 
     pub fn id(&self) -> GLWindowId {
         self.glutin_window.id()
     }
 
-    pub fn create_event_loop_waker(&self) -> Box<GLWindowEventLoopWaker> {
-        box GLWindowEventLoopWaker
+    /// The context version negotiated when the window got built.
+    pub fn gl_version(&self) -> GLContextVersion {
+        self.gl_version
+    }
+
+    /// The pixel format that got picked for the requested attributes.
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.glutin_window.get_pixel_format()
+    }
+
+    /// Ask for a `GLEvent::Frame` event, sent when it's time to draw.
+    pub fn request_redraw(&self) {
+        if let Some(state) = self.windows_state.borrow_mut().get_mut(&self.id()) {
+            state.frame_pacer.redraw_requested = true;
+        }
+    }
+
+    /// Like `GLMethods::swap_buffers`, but returns the error instead of
+    /// logging it.
+    pub fn try_swap_buffers(&self) -> Result<(), Error> {
+        self.before_swap();
+        try!(self.glutin_window.swap_buffers().map_err(|err| self.context_error(err)));
+        self.after_swap()
+    }
+
+    /// Like `GLMethods::make_current`, but returns the error instead of
+    /// logging it.
+    pub fn try_make_current(&self) -> Result<(), Error> {
+        unsafe {
+            self.glutin_window.make_current().map_err(|err| self.context_error(err))
+        }
+    }
+
+    /// Only present the damaged parts of the window. Rects are in window
+    /// coordinates, with the origin at the top left. Falls back to a full
+    /// swap if EGL_KHR_swap_buffers_with_damage (or the EXT variant) is not
+    /// available.
+    pub fn swap_buffers_with_damage(&self, damage: &[Rect<i32>]) -> Result<(), Error> {
+        let (swapper, height) = match (self.damage_swapper.as_ref(), self.glutin_window.get_inner_size()) {
+            (Some(swapper), Some((_, height))) => (swapper, height),
+            _ => return self.try_swap_buffers(),
+        };
+        // The swapper presents the current surface, which must be ours.
+        if !self.glutin_window.is_current() {
+            try!(self.try_make_current());
+        }
+        self.before_swap();
+        let mut damage = damage.to_vec();
+        // An empty damage list means the whole window.
+        if self.frame_stats_overlay.get() && !damage.is_empty() {
+            let (width, overlay_height) = frame_stats::OVERLAY_SIZE;
+            damage.push(Rect::new(Point2D::new(0, height as i32 - overlay_height),
+                                  Size2D::new(width, overlay_height)));
+        }
+        if !swapper.swap(&damage, height) {
+            warn!("Swapping buffers with damage failed. Doing a full swap.");
+            try!(self.glutin_window.swap_buffers().map_err(|err| self.context_error(err)));
+        }
+        self.after_swap()
+    }
+
+    fn before_swap(&self) {
+        if self.frame_stats_overlay.get() {
+            if let Some(ref overlay) = self.overlay {
+                overlay.draw(&*self.gl, &self.frame_stats(), frame_interval());
+            }
+        }
+    }
+
+    fn after_swap(&self) -> Result<(), Error> {
+        if let Some(reset_status) = self.reset_status {
+            let status = reset_status();
+            if status != gl::NO_ERROR {
+                error!("GPU reset detected (0x{:x})", status);
+                return Err(self.context_error(glutin::ContextError::ContextLost));
+            }
+        }
+        let now = Instant::now();
+        self.frame_timings.borrow_mut().record_swap(now, frame_interval());
+        if let Some(state) = self.windows_state.borrow_mut().get_mut(&self.id()) {
+            state.frame_pacer.on_swap(now);
+        }
+        Ok(())
+    }
+
+    /// Stop logging the KHR_debug messages with these ids. Only works with
+    /// debug contexts, see `GLWindowBuilder::with_gl_debug_flag`. The
+    /// context must be current.
+    pub fn ignore_gl_debug_messages(&self, ids: &[u32]) {
+        if let Some(ref gl_debug) = self.gl_debug {
+            gl_debug.ignore(ids);
+        }
+    }
+
+    /// Make `get_gl` return a `Gl` that logs glGetError codes after every
+    /// call, along with the name of the call. Only affects later `get_gl`
+    /// calls. Off by default, as glGetError stalls the pipeline.
+    pub fn set_gl_error_checking(&self, enabled: bool) {
+        self.gl_error_checking.set(enabled);
+    }
+
+    /// Drain the pending glGetError codes. The context must be current.
+    pub fn gl_errors(&self) -> Vec<gl::GLenum> {
+        gl_debug::drain_errors(&*self.gl)
+    }
+
+    /// Schedule a `GLEvent::ContextLost` if the context is gone.
+    fn context_error(&self, err: glutin::ContextError) -> Error {
+        if let glutin::ContextError::ContextLost = err {
+            if let Some(state) = self.windows_state.borrow_mut().get_mut(&self.id()) {
+                state.context_lost = true;
+            }
+        }
+        Error::Context(err)
+    }
+
+    /// Rebuild the GL context, after a `GLEvent::ContextLost`. GPU resources
+    /// must be recreated, with the `Gl` returned by `get_gl`.
+    ///
+    /// This is a hard limitation: glutin can't rebuild a context alone, as
+    /// contexts are tied to native windows. So the native window gets
+    /// replaced, which might flicker, and its id changes. The new id is
+    /// returned. The title, position, size, size limits, visibility,
+    /// maximized and fullscreen states of the window are carried over, as
+    /// well as the cursor, its grab and the pointer lock.
+    ///
+    /// A GPU reset loses all the contexts sharing lists. A window built with
+    /// `with_shared_lists` must be given the window it shares with, after
+    /// that one got recreated.
+    pub fn recreate_context(&mut self,
+                            event_loop: &GLEventLoop,
+                            shared_lists: Option<&GLWindow>)
+                            -> Result<GLWindowId, Error> {
+        if self.attributes.borrow().shared_lists && shared_lists.is_none() {
+            return Err(Error::Unsupported("recreating a shared context without the window it shares with"));
+        }
+        let (width, height) = self.glutin_window.get_inner_size().unwrap_or((800, 600));
+        let builder = GLWindowBuilder {
+            width: width,
+            height: height,
+            gl_preferences: vec![self.gl_version],
+            attributes: self.attributes.borrow().clone(),
+            shared_lists: shared_lists,
+        };
+        let (glutin_window, version) = try!(builder.build_glutin_window(event_loop, self.visible.get()));
+        if let Some((x, y)) = self.glutin_window.get_position() {
+            glutin_window.set_position(x, y);
+        }
+        let window = try!(GLWindow::new(event_loop, glutin_window, version, builder.attributes));
+        window.set_title(&self.title.borrow());
+        window.visible.set(self.visible.get());
+        window.windowed_geometry.set(self.windowed_geometry.get());
+        window.frame_stats_overlay.set(self.frame_stats_overlay.get());
+        window.gl_error_checking.set(self.gl_error_checking.get());
+        {
+            let mut windows = event_loop.windows_state.borrow_mut();
+            if let Some(previous) = windows.remove(&self.id()) {
+                if let Some(state) = windows.get_mut(&window.id()) {
+                    state.inherit(previous);
+                }
+            }
+        }
+        // Drops the previous window.
+        *self = window;
+        Ok(self.id())
+    }
+
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_timings.borrow().stats()
+    }
+
+    /// Draw a graph of the recent frame times right before swapping buffers.
+    pub fn set_frame_stats_overlay(&self, enabled: bool) {
+        self.frame_stats_overlay.set(enabled);
     }
 
     pub fn show(&self) {
-        self.glutin_window.show()
+        self.glutin_window.show();
+        self.visible.set(true);
+    }
+
+    pub fn hide(&self) {
+        self.glutin_window.hide();
+        self.visible.set(false);
+    }
+
+    /// Position of the top left corner of the window decorations.
+    pub fn set_position(&self, x: i32, y: i32) {
+        self.glutin_window.set_position(x, y)
+    }
+
+    pub fn set_inner_size(&self, width: u32, height: u32) {
+        self.glutin_window.set_inner_size(width, height)
+    }
+
+    pub fn set_min_size(&self, size: Option<(u32, u32)>) {
+        self.glutin_window.set_min_dimensions(size);
+        self.attributes.borrow_mut().min_size = size;
+    }
+
+    pub fn set_max_size(&self, size: Option<(u32, u32)>) {
+        self.glutin_window.set_max_dimensions(size);
+        self.attributes.borrow_mut().max_size = size;
+    }
+
+    /// Only supported on X11.
+    pub fn minimize(&self) -> Result<(), Error> {
+        match self.x11 {
+            Some(ref x11) if x11.iconify() => Ok(()),
+            Some(_) => Err(Error::Platform("XIconifyWindow failed".to_owned())),
+            None => Err(Error::Unsupported("minimize")),
+        }
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        self.glutin_window.set_maximized(maximized);
+        self.attributes.borrow_mut().maximized = maximized;
+    }
+
+    /// Borderless fullscreen on `monitor`, or back to a regular window if
+    /// None. Leaving fullscreen restores the position and size the window
+    /// had before. Sends a `GLEvent::FullscreenChanged` if the state changes.
+    pub fn set_fullscreen(&self, monitor: Option<&Monitor>) {
+        let was_fullscreen = self.is_fullscreen();
+        if !was_fullscreen {
+            let position = self.glutin_window.get_position();
+            let size = self.glutin_window.get_inner_size();
+            self.windowed_geometry.set(position.and_then(|position| size.map(|size| (position, size))));
+        }
+
+        let id = monitor.map(|monitor| monitor.id.clone());
+        let fullscreen = id.is_some();
+        self.glutin_window.set_fullscreen(id.clone());
+        self.attributes.borrow_mut().fullscreen = id;
+
+        if was_fullscreen && !fullscreen {
+            if let Some(((x, y), (width, height))) = self.windowed_geometry.take() {
+                self.glutin_window.set_position(x, y);
+                self.glutin_window.set_inner_size(width, height);
+            }
+        }
+        if fullscreen != was_fullscreen {
+            if let Some(state) = self.windows_state.borrow_mut().get_mut(&self.id()) {
+                state.fullscreen_changed = Some(fullscreen);
+            }
+        }
+    }
+
+    /// For `requestFullscreen()`. Goes fullscreen on the monitor the window
+    /// is on.
+    pub fn enter_fullscreen(&self) {
+        if !self.is_fullscreen() {
+            self.set_fullscreen(Some(&self.current_monitor()));
+        }
+    }
+
+    pub fn exit_fullscreen(&self) {
+        self.set_fullscreen(None);
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.attributes.borrow().fullscreen.is_some()
+    }
+
+    /// The monitor the window is on.
+    pub fn current_monitor(&self) -> Monitor {
+        Monitor::new(self.glutin_window.get_current_monitor())
+    }
+
+    /// Raise and focus the window. Only supported on X11, where the window
+    /// manager might still decline.
+    pub fn focus(&self) -> Result<(), Error> {
+        match self.x11 {
+            Some(ref x11) if x11.activate() => Ok(()),
+            Some(_) => Err(Error::Platform("Couldn't send _NET_ACTIVE_WINDOW".to_owned())),
+            None => Err(Error::Unsupported("focus")),
+        }
+    }
+
+    /// Highlight the taskbar entry until the window gets focused. Only
+    /// supported on X11.
+    pub fn request_user_attention(&self) -> Result<(), Error> {
+        match self.x11 {
+            Some(ref x11) if x11.demand_attention() => Ok(()),
+            Some(_) => Err(Error::Platform("Couldn't send _NET_WM_STATE".to_owned())),
+            None => Err(Error::Unsupported("request_user_attention")),
+        }
     }
 
     pub fn set_cursor(&self, cursor: ServoCursor) {
         let glutin_cursor = servo_cursor_to_glutin_cursor(cursor);
+        if let Some(state) = self.windows_state.borrow_mut().get_mut(&self.id()) {
+            state.cursor = glutin_cursor;
+            state.custom_cursor = None;
+            if state.is_cursor_hidden_by_grab() {
+                return;
+            }
+        }
         self.glutin_window.set_cursor(glutin_cursor);
     }
 
+    /// For CSS `cursor: url(...)`. `rgba` holds `size.0 * size.1` pixels,
+    /// without premultiplied alpha. Replaced by the next `set_cursor` call.
+    /// Only supported on X11.
+    pub fn set_custom_cursor(&self, rgba: &[u8], size: (u32, u32), hotspot: (u32, u32)) -> Result<(), Error> {
+        assert_eq!(rgba.len(), (size.0 * size.1 * 4) as usize);
+        let mut windows = self.windows_state.borrow_mut();
+        let state = windows.get_mut(&self.id()).expect("Window not registered");
+        let previous = state.custom_cursor.take();
+        state.custom_cursor = Some(CustomCursor {
+            rgba: rgba.to_vec(),
+            size: size,
+            hotspot: hotspot,
+        });
+        let result = state.update_cursor_state();
+        if result.is_err() {
+            state.custom_cursor = previous;
+        }
+        result
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) -> Result<(), Error> {
+        let mut windows = self.windows_state.borrow_mut();
+        let state = windows.get_mut(&self.id()).expect("Window not registered");
+        let previous = state.cursor_visible;
+        state.cursor_visible = visible;
+        let result = state.update_cursor_state();
+        if result.is_err() {
+            state.cursor_visible = previous;
+        }
+        result
+    }
+
+    /// `CursorGrab::Locked` is the pointer lock mode: the cursor is hidden
+    /// and kept at the center of the window, and mouse moves are reported as
+    /// `GLEvent::MouseMotion` deltas.
+    pub fn set_cursor_grab(&self, grab: CursorGrab) -> Result<(), Error> {
+        self.windows_state
+            .borrow_mut()
+            .get_mut(&self.id())
+            .expect("Window not registered")
+            .set_cursor_grab(grab)
+    }
+
+    pub fn cursor_grab(&self) -> CursorGrab {
+        self.windows_state
+            .borrow()
+            .get(&self.id())
+            .map_or(CursorGrab::None, |state| state.cursor_grab)
+    }
+
     pub fn set_title(&self, title: &str) {
         self.glutin_window.set_title(title);
+        *self.title.borrow_mut() = title.to_owned();
+    }
+
+    /// Scale factor of the monitor the window is on. See
+    /// `GLEvent::ScaleFactorChanged`.
+    pub fn scale_factor(&self) -> f32 {
+        self.windows_state
+            .borrow()
+            .get(&self.id())
+            .map_or_else(|| self.glutin_window.hidpi_factor(), |state| state.scale_factor)
+    }
+
+    /// Cursor position, None until the cursor moves in the window and once
+    /// it left it.
+    pub fn mouse_position(&self) -> Option<TypedPoint2D<f32, DevicePixel>> {
+        self.windows_state
+            .borrow()
+            .get(&self.id())
+            .and_then(|state| state.mouse_position)
+    }
+
+    /// Convert a position in the window to CSS pixels, at a zoom level of 1.
+    pub fn to_logical(&self, point: TypedPoint2D<f32, DevicePixel>) -> TypedPoint2D<f32, LogicalPixel> {
+        let scale_factor = self.scale_factor();
+        TypedPoint2D::new(point.x / scale_factor, point.y / scale_factor)
+    }
+
+    /// Everything is in device pixels. Use the logical_* methods of
+    /// `WindowGeometry` for device independent values.
+    ///
+    /// The position and size are the ones reported by the last Moved and
+    /// Resized events, so this doesn't query the platform.
+    pub fn geometry(&self) -> WindowGeometry {
+        let (position, size, margins, scale_factor) = {
+            let windows = self.windows_state.borrow();
+            let state = windows.get(&self.id());
+            (state.and_then(|state| state.position),
+             state.and_then(|state| state.inner_size),
+             state.map(|state| state.margins),
+             state.map(|state| state.scale_factor))
+        };
+        let (x, y) = position.or_else(|| self.glutin_window.get_position())
+            .expect("Failed to get window position.");
+        let (width, height) = size.or_else(|| self.glutin_window.get_inner_size())
+            .expect("Failed to get window inner size.");
+        let scale_factor = scale_factor.unwrap_or_else(|| self.glutin_window.hidpi_factor());
+        let (top, right, bottom, left) = margins.unwrap_or_else(|| compute_margins(&self.glutin_window));
+
+        WindowGeometry {
+            inner_position: TypedPoint2D::new(x + left as i32, y + top as i32),
+            inner_size: TypedSize2D::new(width, height),
+            outer_position: TypedPoint2D::new(x, y),
+            outer_size: TypedSize2D::new(width + left + right, height + top + bottom),
+            margins: TypedSideOffsets2D::new(top, right, bottom, left),
+            scale_factor: scale_factor,
+        }
     }
 
     pub fn get_geometry(&self) -> DrawableGeometry {
+        let geometry = self.geometry();
+        let margins = geometry.margins;
         DrawableGeometry {
-            view_size: self.glutin_window
-                .get_inner_size()
-                .expect("Failed to get window inner size."),
-            margins: (0, 0, 0, 0),
-            position: self.glutin_window
-                .get_position()
-                .expect("Failed to get window position."),
-            hidpi_factor: self.glutin_window.hidpi_factor(),
+            view_size: (geometry.inner_size.width, geometry.inner_size.height),
+            margins: (margins.top, margins.right, margins.bottom, margins.left),
+            position: (geometry.outer_position.x, geometry.outer_position.y),
+            hidpi_factor: geometry.scale_factor,
         }
     }
 }
 
-pub struct GLWindowEventLoopWaker;
+impl Drop for GLWindow {
+    fn drop(&mut self) {
+        self.windows_state.borrow_mut().remove(&self.glutin_window.id());
+    }
+}
+
+/// Top, right, bottom and left thickness of the decorations. glutin only
+/// gives us the outer and inner sizes, so assume the side and bottom borders
+/// are as thick, and that the rest is the title bar.
+fn compute_margins(glutin_window: &glutin::GlWindow) -> (u32, u32, u32, u32) {
+    let (width, height) = match glutin_window.get_inner_size() {
+        Some(size) => size,
+        None => return (0, 0, 0, 0),
+    };
+    let (outer_width, outer_height) = glutin_window.get_outer_size().unwrap_or((width, height));
+    let border = outer_width.saturating_sub(width) / 2;
+    let vertical = outer_height.saturating_sub(height);
+    let bottom = cmp::min(border, vertical);
+    (vertical - bottom, border, bottom, border)
+}
+
+/// Make the context current and load the GL functions matching its API.
+/// Fails if the context can't be made current, e.g. right after a GPU reset.
+fn load_gl(glutin_window: &glutin::GlWindow, gl_version: GLContextVersion) -> Result<Rc<gl::Gl>, Error> {
+    let gl = unsafe {
+        try!(glutin_window.make_current());
+        match gl_version.api {
+            GLApi::OpenGl => gl::GlFns::load_with(|s| glutin_window.get_proc_address(s) as *const _),
+            GLApi::OpenGlEs => gl::GlesFns::load_with(|s| glutin_window.get_proc_address(s) as *const _),
+        }
+    };
+    info!("Got a {:?} context: {}", gl_version, gl.get_string(gl::VERSION));
+    info!("Pixel format: {:?}", glutin_window.get_pixel_format());
+    Ok(gl)
+}
+
+type GetGraphicsResetStatusFn = extern "system" fn() -> gl::GLenum;
+
+/// glGetGraphicsResetStatus comes from ARB_robustness in GL, and from
+/// KHR_robustness or EXT_robustness in GLES.
+fn load_reset_status(glutin_window: &glutin::GlWindow, gl: &gl::Gl) -> Option<GetGraphicsResetStatusFn> {
+    let candidates = [("GL_KHR_robustness", "glGetGraphicsResetStatusKHR"),
+                      ("GL_ARB_robustness", "glGetGraphicsResetStatusARB"),
+                      ("GL_EXT_robustness", "glGetGraphicsResetStatusEXT")];
+    candidates.iter()
+        .filter(|&&(extension, _)| gl_debug::has_extension(gl, extension))
+        .map(|&(_, function)| glutin_window.get_proc_address(function))
+        .find(|ptr| !ptr.is_null())
+        .map(|ptr| unsafe { mem::transmute::<_, GetGraphicsResetStatusFn>(ptr) })
+}
+
+/// An offscreen context. See `GLWindowBuilder::build_headless`.
+pub struct GLHeadlessContext {
+    gl: Rc<gl::Gl>,
+    gl_version: GLContextVersion,
+    glutin_window: glutin::GlWindow,
+    headless_windows: HeadlessWindows,
+}
+
+impl GLMethods for GLHeadlessContext {
+    fn swap_buffers(&self) {
+        // Nothing is presented.
+    }
+    fn make_current(&self) -> Result<(),()> {
+        unsafe {
+            self.glutin_window.make_current().map_err(|err| {
+                error!("Failed to make the context current: {}", err);
+            })
+        }
+    }
+    fn get_gl(&self) -> Rc<gl::Gl> {
+        self.gl.clone()
+    }
+}
+
+impl GLHeadlessContext {
+    pub fn gl_version(&self) -> GLContextVersion {
+        self.gl_version
+    }
+}
+
+impl Drop for GLHeadlessContext {
+    fn drop(&mut self) {
+        self.headless_windows.borrow_mut().remove(&self.glutin_window.id());
+    }
+}
+
+/// Outlives the loop safely: waking a dropped loop does nothing.
+pub struct GLWindowEventLoopWaker {
+    interrupter: Arc<LoopInterrupter>,
+    wake_state: Arc<WakeState>,
+}
+
+impl GLWindowEventLoopWaker {
+    /// Wake the loop up. The reason is delivered as a `GLEvent::Woken` event.
+    /// Multiple wake ups happening before the loop runs are coalesced into
+    /// a single Idle event, but all the reasons are delivered.
+    pub fn wake_with(&self, reason: WakeReason) {
+        self.wake_state.wake(Some(reason));
+        self.interrupter.interrupt();
+    }
+}
 
 impl EventLoopWaker for GLWindowEventLoopWaker {
     fn clone(&self) -> Box<EventLoopWaker + Send> {
-        box GLWindowEventLoopWaker
+        box GLWindowEventLoopWaker {
+            interrupter: self.interrupter.clone(),
+            wake_state: self.wake_state.clone(),
+        }
     }
     fn wake(&self) {
-        LOOP.interrupt();
+        self.wake_state.wake(None);
+        self.interrupter.interrupt();
+    }
+}
+
+fn glutin_phase_to_touch_event_type(phase: glutin::TouchPhase) -> TouchEventType {
+    match phase {
+        glutin::TouchPhase::Started => TouchEventType::Down,
+        glutin::TouchPhase::Moved => TouchEventType::Move,
+        glutin::TouchPhase::Ended => TouchEventType::Up,
+        glutin::TouchPhase::Cancelled => TouchEventType::Cancel,
     }
 }
 
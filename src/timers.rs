@@ -0,0 +1,157 @@
+//! Timers fired by the event loop.
+
+use std::cmp;
+use std::time::{Duration, Instant};
+
+/// Shortest interval of a repeating timer. Shorter ones would fire on every
+/// iteration and keep the loop spinning.
+const MIN_INTERVAL_MS: u64 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+struct Timer {
+    id: TimerId,
+    token: u64,
+    deadline: Instant,
+    interval: Option<Duration>,
+}
+
+pub struct Timers {
+    timers: Vec<Timer>,
+    next_id: u64,
+}
+
+impl Timers {
+    pub fn new() -> Timers {
+        Timers {
+            timers: vec![],
+            next_id: 0,
+        }
+    }
+
+    /// Repeat every `interval` if set, clamped to `MIN_INTERVAL_MS`.
+    pub fn add(&mut self, deadline: Instant, interval: Option<Duration>, token: u64) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.push(Timer {
+            id: id,
+            token: token,
+            deadline: deadline,
+            interval: interval.map(|i| cmp::max(i, Duration::from_millis(MIN_INTERVAL_MS))),
+        });
+        id
+    }
+
+    /// Returns false if the timer already fired or has been cancelled.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let len = self.timers.len();
+        self.timers.retain(|timer| timer.id != id);
+        self.timers.len() != len
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers.iter().map(|timer| timer.deadline).min()
+    }
+
+    /// Remove the earliest timer due at `now`, or reschedule it if it
+    /// repeats. Returns its id and token. Timers fire one at a time so that
+    /// the loop can stop in between.
+    pub fn take_next_due(&mut self, now: Instant) -> Option<(TimerId, u64)> {
+        let index = {
+            let due = self.timers
+                          .iter()
+                          .enumerate()
+                          .filter(|&(_, timer)| timer.deadline <= now)
+                          .min_by_key(|&(_, timer)| timer.deadline);
+            match due {
+                Some((index, _)) => index,
+                None => return None,
+            }
+        };
+        let fired = (self.timers[index].id, self.timers[index].token);
+        match self.timers[index].interval {
+            Some(interval) => {
+                let timer = &mut self.timers[index];
+                // Don't try to catch up on missed ticks.
+                let next = timer.deadline + interval;
+                timer.deadline = if next > now { next } else { now + interval };
+            }
+            None => {
+                self.timers.remove(index);
+            }
+        }
+        Some(fired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn one_shot_fires_once() {
+        let start = Instant::now();
+        let mut timers = Timers::new();
+        let id = timers.add(start + ms(10), None, 7);
+        assert_eq!(timers.take_next_due(start + ms(5)), None);
+        assert_eq!(timers.take_next_due(start + ms(10)), Some((id, 7)));
+        assert_eq!(timers.take_next_due(start + ms(20)), None);
+        assert_eq!(timers.next_deadline(), None);
+    }
+
+    #[test]
+    fn repeating_keeps_its_phase_when_on_time() {
+        let start = Instant::now();
+        let mut timers = Timers::new();
+        let id = timers.add(start + ms(10), Some(ms(10)), 1);
+        assert_eq!(timers.take_next_due(start + ms(12)), Some((id, 1)));
+        assert_eq!(timers.next_deadline(), Some(start + ms(20)));
+    }
+
+    #[test]
+    fn repeating_skips_missed_ticks() {
+        let start = Instant::now();
+        let mut timers = Timers::new();
+        let id = timers.add(start + ms(10), Some(ms(10)), 1);
+        // Three ticks late: fire once, then wait a full interval.
+        assert_eq!(timers.take_next_due(start + ms(45)), Some((id, 1)));
+        assert_eq!(timers.next_deadline(), Some(start + ms(55)));
+    }
+
+    #[test]
+    fn zero_interval_is_clamped() {
+        let start = Instant::now();
+        let mut timers = Timers::new();
+        timers.add(start, Some(ms(0)), 1);
+        assert!(timers.take_next_due(start).is_some());
+        assert_eq!(timers.next_deadline(), Some(start + ms(MIN_INTERVAL_MS)));
+    }
+
+    #[test]
+    fn cancel_reports_whether_the_timer_was_pending() {
+        let start = Instant::now();
+        let mut timers = Timers::new();
+        let one_shot = timers.add(start, None, 1);
+        let repeating = timers.add(start, Some(ms(10)), 2);
+        while timers.take_next_due(start).is_some() {}
+        assert!(!timers.cancel(one_shot));
+        assert!(timers.cancel(repeating));
+        assert!(!timers.cancel(repeating));
+    }
+
+    #[test]
+    fn due_timers_fire_in_order() {
+        let start = Instant::now();
+        let mut timers = Timers::new();
+        let late = timers.add(start + ms(5), None, 1);
+        let early = timers.add(start, None, 2);
+        assert_eq!(timers.take_next_due(start + ms(5)), Some((early, 2)));
+        assert_eq!(timers.take_next_due(start + ms(5)), Some((late, 1)));
+        assert_eq!(timers.take_next_due(start + ms(5)), None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Wake ups, shared between the event loop and its wakers.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use WakeReason;
+
+#[derive(Default)]
+pub struct WakeState {
+    woken: AtomicBool,
+    reasons: Mutex<VecDeque<WakeReason>>,
+}
+
+impl WakeState {
+    /// The reason is pushed before the flag is set, see `take_woken`.
+    pub fn wake(&self, reason: Option<WakeReason>) {
+        if let Some(reason) = reason {
+            self.reasons.lock().unwrap().push_back(reason);
+        }
+        self.woken.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_woken(&self) -> bool {
+        self.woken.load(Ordering::SeqCst)
+    }
+
+    /// Clears the flag. Must be called before popping the reasons: a reason
+    /// pushed after the last pop then comes with the flag set again, which
+    /// keeps the loop from sleeping, so it's popped on the next iteration
+    /// rather than lost.
+    pub fn take_woken(&self) -> bool {
+        self.woken.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn pop_reason(&self) -> Option<WakeReason> {
+        self.reasons.lock().unwrap().pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn reason(value: usize) -> Option<WakeReason> {
+        Some(Box::new(value))
+    }
+
+    /// What the loop does on every iteration.
+    fn take(state: &WakeState) -> (bool, Vec<usize>) {
+        let woken = state.take_woken();
+        let mut reasons = vec![];
+        while let Some(reason) = state.pop_reason() {
+            reasons.push(*reason.downcast::<usize>().unwrap());
+        }
+        (woken, reasons)
+    }
+
+    #[test]
+    fn wake_ups_are_coalesced() {
+        let state = WakeState::default();
+        state.wake(reason(1));
+        state.wake(None);
+        state.wake(reason(2));
+        assert!(state.is_woken());
+        assert_eq!(take(&state), (true, vec![1, 2]));
+        assert!(!state.is_woken());
+        assert_eq!(take(&state), (false, vec![]));
+    }
+
+    #[test]
+    fn pending_reasons_keep_the_flag_set() {
+        const WAKERS: usize = 4;
+        const WAKE_UPS: usize = 10000;
+        let state = Arc::new(WakeState::default());
+        let wakers: Vec<_> = (0..WAKERS).map(|_| {
+            let state = state.clone();
+            thread::spawn(move || for i in 0..WAKE_UPS {
+                state.wake(reason(i));
+            })
+        }).collect();
+        let mut received = 0;
+        // Drain while the wakers run.
+        while Arc::strong_count(&state) > 1 {
+            received += take(&state).1.len();
+        }
+        for waker in wakers {
+            waker.join().unwrap();
+        }
+        // Whatever the interleaving, reasons left behind by a drain must
+        // come with the flag, or the loop would sleep on them.
+        let (woken, reasons) = take(&state);
+        assert!(woken || reasons.is_empty());
+        assert_eq!(received + reasons.len(), WAKERS * WAKE_UPS);
+    }
+}
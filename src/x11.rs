@@ -0,0 +1,208 @@
+//! Window management requests, custom cursors and pointer queries, which
+//! glutin doesn't expose, sent to the X server directly. None of them is
+//! available on Wayland or on other platforms.
+
+use glutin;
+#[cfg(target_os = "linux")]
+use glutin::os::unix::WindowExt;
+#[cfg(target_os = "linux")]
+use std::ffi::CString;
+use std::fmt;
+#[cfg(target_os = "linux")]
+use std::os::raw::{c_int, c_long, c_uint, c_ulong};
+#[cfg(target_os = "linux")]
+use std::slice;
+#[cfg(target_os = "linux")]
+use x11_dl::{xcursor, xlib};
+
+#[cfg(target_os = "linux")]
+const NET_WM_STATE_ADD: c_long = 1;
+/// Tells the window manager that the request comes from an application, as
+/// opposed to a pager.
+#[cfg(target_os = "linux")]
+const SOURCE_APPLICATION: c_long = 1;
+
+pub struct X11Window {
+    #[cfg(target_os = "linux")]
+    xlib: xlib::Xlib,
+    /// None if libXcursor is missing. Only needed for custom cursors.
+    #[cfg(target_os = "linux")]
+    xcursor: Option<xcursor::Xcursor>,
+    /// Owned by glutin, valid as long as the window is.
+    #[cfg(target_os = "linux")]
+    display: *mut xlib::Display,
+    #[cfg(target_os = "linux")]
+    window: c_ulong,
+}
+
+impl X11Window {
+    /// Returns None if the window isn't an X11 one, or if Xlib can't be
+    /// loaded.
+    #[cfg(target_os = "linux")]
+    pub fn load(window: &glutin::GlWindow) -> Option<X11Window> {
+        let (display, x_window) = match (window.get_xlib_display(), window.get_xlib_window()) {
+            (Some(display), Some(x_window)) => (display, x_window),
+            _ => return None,
+        };
+        let xlib = match xlib::Xlib::open() {
+            Ok(xlib) => xlib,
+            Err(err) => {
+                warn!("Couldn't load Xlib: {}", err);
+                return None;
+            }
+        };
+        Some(X11Window {
+            xlib: xlib,
+            xcursor: xcursor::Xcursor::open().ok(),
+            display: display as *mut xlib::Display,
+            window: x_window,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn load(_window: &glutin::GlWindow) -> Option<X11Window> {
+        None
+    }
+
+    /// Returns false if the request couldn't be sent.
+    #[cfg(target_os = "linux")]
+    pub fn iconify(&self) -> bool {
+        unsafe {
+            let screen = (self.xlib.XDefaultScreen)(self.display);
+            let sent = (self.xlib.XIconifyWindow)(self.display, self.window, screen) != 0;
+            (self.xlib.XFlush)(self.display);
+            sent
+        }
+    }
+
+    /// Ask the window manager to raise and focus the window. It might
+    /// decline, e.g. to prevent focus stealing.
+    #[cfg(target_os = "linux")]
+    pub fn activate(&self) -> bool {
+        self.send_wm_message("_NET_ACTIVE_WINDOW", [SOURCE_APPLICATION, xlib::CurrentTime as c_long, 0, 0, 0])
+    }
+
+    /// The window manager clears the state once the window gets focused.
+    #[cfg(target_os = "linux")]
+    pub fn demand_attention(&self) -> bool {
+        let demands_attention = self.intern_atom("_NET_WM_STATE_DEMANDS_ATTENTION");
+        self.send_wm_message("_NET_WM_STATE",
+                             [NET_WM_STATE_ADD, demands_attention as c_long, 0, SOURCE_APPLICATION, 0])
+    }
+
+    /// `rgba` holds `width * height` non premultiplied pixels. Returns false
+    /// if the cursor couldn't be created.
+    #[cfg(target_os = "linux")]
+    pub fn define_cursor(&self, rgba: &[u8], (width, height): (u32, u32), (hot_x, hot_y): (u32, u32)) -> bool {
+        let xcursor = match self.xcursor {
+            Some(ref xcursor) => xcursor,
+            None => return false,
+        };
+        unsafe {
+            let image = (xcursor.XcursorImageCreate)(width as c_int, height as c_int);
+            if image.is_null() {
+                return false;
+            }
+            (*image).xhot = hot_x;
+            (*image).yhot = hot_y;
+            let pixels = slice::from_raw_parts_mut((*image).pixels, (width * height) as usize);
+            for (pixel, rgba) in pixels.iter_mut().zip(rgba.chunks(4)) {
+                // Xcursor wants premultiplied ARGB.
+                let alpha = rgba[3] as u32;
+                let premultiply = |channel: u8| channel as u32 * alpha / 255;
+                *pixel = alpha << 24 | premultiply(rgba[0]) << 16 | premultiply(rgba[1]) << 8 |
+                         premultiply(rgba[2]);
+            }
+            let cursor = (xcursor.XcursorImageLoadCursor)(self.display, image);
+            (xcursor.XcursorImageDestroy)(image);
+            if cursor == 0 {
+                return false;
+            }
+            // The server keeps the cursor alive while the window uses it.
+            (self.xlib.XDefineCursor)(self.display, self.window, cursor);
+            (self.xlib.XFreeCursor)(self.display, cursor);
+            (self.xlib.XFlush)(self.display);
+        }
+        true
+    }
+
+    /// Position of the pointer relative to the window. Works during a file
+    /// drag, when the drag source grabs the pointer and the window doesn't
+    /// get motion events.
+    #[cfg(target_os = "linux")]
+    pub fn pointer_position(&self) -> Option<(i32, i32)> {
+        let (mut root, mut child) = (0, 0);
+        let (mut root_x, mut root_y, mut x, mut y) = (0, 0, 0, 0);
+        let mut mask: c_uint = 0;
+        let same_screen = unsafe {
+            (self.xlib.XQueryPointer)(self.display, self.window, &mut root, &mut child,
+                                      &mut root_x, &mut root_y, &mut x, &mut y, &mut mask)
+        };
+        if same_screen != 0 {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn intern_atom(&self, name: &str) -> xlib::Atom {
+        let name = CString::new(name).unwrap();
+        unsafe { (self.xlib.XInternAtom)(self.display, name.as_ptr(), xlib::False) }
+    }
+
+    /// EWMH requests are client messages sent to the root window.
+    #[cfg(target_os = "linux")]
+    fn send_wm_message(&self, message_type: &str, data: [c_long; 5]) -> bool {
+        let mut event = xlib::XClientMessageEvent {
+            type_: xlib::ClientMessage,
+            serial: 0,
+            send_event: xlib::True,
+            display: self.display,
+            window: self.window,
+            message_type: self.intern_atom(message_type),
+            format: 32,
+            data: xlib::ClientMessageData::new(),
+        };
+        event.data.as_longs_mut().copy_from_slice(&data);
+        let mut event = xlib::XEvent::from(event);
+        unsafe {
+            let root = (self.xlib.XDefaultRootWindow)(self.display);
+            let mask = xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask;
+            let sent = (self.xlib.XSendEvent)(self.display, root, xlib::False, mask, &mut event) != 0;
+            (self.xlib.XFlush)(self.display);
+            sent
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn iconify(&self) -> bool {
+        false
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn activate(&self) -> bool {
+        false
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn demand_attention(&self) -> bool {
+        false
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn pointer_position(&self) -> Option<(i32, i32)> {
+        None
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn define_cursor(&self, _rgba: &[u8], _size: (u32, u32), _hotspot: (u32, u32)) -> bool {
+        false
+    }
+}
+
+impl fmt::Debug for X11Window {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "X11Window")
+    }
+}